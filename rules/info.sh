@@ -1,12 +1,20 @@
+## description: Rule information with the latest changes.
+## version: 1.0
+## changelog: 1.0 first version
+## tags: rules, info
+## icon: 📜
 discordsh --webhook-url="${WEBHOOK}" \
     --username "${SERVERNAME:-"RulePot"}" \
     --avatar "${AVATAR:-"https://pics.craiyon.com/2023-11-30/5vCa4-Q6QNKjDMwBmnTbNQ.webp"}" \
+    ${TEXT:+--text} ${TEXT:+"${TEXT}"} \
     --title "Rule information." \
     --description "${DESCRIPTION:-"Simple information about any rule.\\n1. new changes..\\n2. new ban system.."}" \
     --color "${COLOR:-"0x2e954d"}" \
-    --author "RulePot" \
-    --author-icon "${SERVERICON:-"https://pics.craiyon.com/2023-11-30/5vCa4-Q6QNKjDMwBmnTbNQ.webp"}" \
-    --thumbnail "${IMAGE:-"https://cdn-icons-png.flaticon.com/512/10830/10830975.png"}" \
-    --footer "rules" \
-    --footer-icon "${AVATAR:-"https://pics.craiyon.com/2023-11-30/5vCa4-Q6QNKjDMwBmnTbNQ.webp"}" \
+    --author "${AUTHOR:-"RulePot"}" \
+    ${AUTHORURL:+--author-url} ${AUTHORURL:+"${AUTHORURL}"} \
+    --author-icon "${AUTHORICON:-"${SERVERICON:-"https://pics.craiyon.com/2023-11-30/5vCa4-Q6QNKjDMwBmnTbNQ.webp"}"}" \
+    --thumbnail "${THUMBNAIL:-"${IMAGE:-"https://cdn-icons-png.flaticon.com/512/10830/10830975.png"}"}" \
+    ${BANNER:+--image} ${BANNER:+"${BANNER}"} \
+    --footer "${FOOTER:-"rules"}" \
+    --footer-icon "${FOOTERICON:-"${AVATAR:-"https://pics.craiyon.com/2023-11-30/5vCa4-Q6QNKjDMwBmnTbNQ.webp"}"}" \
     --timestamp
\ No newline at end of file
@@ -1,20 +1,2063 @@
 #!/bin/bash
 
 set -e
-shopt -s expand_aliases
 
-export CONFIG="${CONFIG:-"conf.sh"}" SETOPT="help"
+## A .ptwebhookt.conf in the current directory or a parent, up to the git repository's root or
+## ${HOME}, holds project defaults (CONFIG, TEMPLATEDIR, TEMPLATE), ${WORKSPACE} is the directory
+## it was found in. It is run as code, so it has to be yours and not writable by anyone else.
+export BASEDIR="$(dirname "$(readlink -f "${BASH_SOURCE[0]}")")" WORKSPACE="${PWD}"
+while [[ ! -f "${WORKSPACE}/.ptwebhookt.conf" ]] && [[ ! -e "${WORKSPACE}/.git" ]] && [[ "${WORKSPACE}" != "${HOME}" ]] && [[ -n "${WORKSPACE}" ]] ; do
+	WORKSPACE="${WORKSPACE%/*}"
+done
+if [[ -f "${WORKSPACE}/.ptwebhookt.conf" ]] ; then
+	if [[ ! -O "${WORKSPACE}/.ptwebhookt.conf" ]] || [[ -n "$(find "${WORKSPACE}/.ptwebhookt.conf" -perm /022)" ]] ; then
+		echo "not loading \"${WORKSPACE}/.ptwebhookt.conf\", it isn't yours or others can write to it."
+		exit 1
+	fi
+	source "${WORKSPACE}/.ptwebhookt.conf"
+fi
+
+export CONFIG="${CONFIG:-"conf.sh"}" SETOPT="help" ACCESSIBLE="${ACCESSIBLE:-"no"}" ASSUMEYES="no" DRYRUN="no" SECRETS="yes"
+VARSFILES=() SEARCH=() VALIDATE=() EXTENDING=() RESOLVING=()
+declare -A OVERRIDES=() VARS=()
+
+export CONFIGDIR="${CONFIGDIR:-"${XDG_CONFIG_HOME:-"${HOME}/.config"}/ptwebhookt"}"
+export SNIPPETDIR="${SNIPPETDIR:-"${CONFIGDIR}/snippets"}"
+export GLOBALS="${GLOBALS:-"${CONFIGDIR}/globals.sh"}"
+export SHAREDDIR="${SHAREDDIR:-"${CONFIGDIR}/shared"}"
+
+## Templates come from --templates-dir, $PTWEBHOOK_TEMPLATES, TEMPLATEDIR (environment or .ptwebhookt.conf),
+## "${CONFIGDIR}/templates" when it exists, or the templates next to this script, in that order.
+if [[ -n "${PTWEBHOOK_TEMPLATES}" ]] ; then
+	TEMPLATEDIR="${PTWEBHOOK_TEMPLATES}"
+elif [[ -z "${TEMPLATEDIR}" ]] && [[ -d "${CONFIGDIR}/templates" ]] ; then
+	TEMPLATEDIR="${CONFIGDIR}/templates"
+fi
+export TEMPLATEDIR="${TEMPLATEDIR:-"${BASEDIR}"}"
+
+export STATEDIR="${STATEDIR:-"${XDG_STATE_HOME:-"${HOME}/.local/state"}/ptwebhookt"}"
+export HISTORYFILE="${HISTORYFILE:-"${STATEDIR}/history"}"
+export PENDINGDIR="${PENDINGDIR:-"${STATEDIR}/pending"}"
+
+## With APPROVAL="yes" messages wait in PENDINGDIR until someone runs --approve,
+## SELFAPPROVE="no" (read from the approver's conf) makes that someone else than the requester,
+## who is the owner of the pending file. For a team, point PENDINGDIR at one directory owned
+## by a shared group and made group-writable and setgid (chgrp team dir ; chmod 2770 dir).
+APPROVAL="no" SELFAPPROVE="yes" APPROVING="no"
+PENDINGSETTINGS=(
+	PROVIDER BODY METHOD PRIORITY NTFYSERVER ZULIPSITE ZULIPBOT ZULIPKEY ZULIPTOPIC
+	SMTPURL SMTPUSER SMTPPASSWORD MAILFROM FLAGS THREADNAME USERAGENT THROTTLE ARCHIVEDIR
+)
+
+## At most this many sends per webhook in the given seconds ("sends/seconds"), empty disables it.
+THROTTLE="10/600"
+
+## Words/regexes nobody should send, a protected profile refuses them instead of asking.
+BLOCKLIST=() PROTECTED="no"
+
+## Variables that have to be set before a template is sent, templates' confs add their own.
+REQUIRED=()
+
+## Longest value a variable may have, as "NAME:length", on top of Discord's own limits.
+MAXLENGTH=()
+
+## Variables the profile fills in that globals, --vars and --shell can't change.
+LOCKED=()
+
+## Largest attachment Discord takes on servers without boosts, and all of a message's together, in bytes.
+ATTACHMENTLIMIT="$(( 10 * 1024 * 1024 ))" ATTACHMENTSLIMIT="$(( 25 * 1024 * 1024 ))"
+
+## Embeds a template adds with embed, they go along with the next message's own embed.
+EMBEDS=()
+
+## Files attached to every message (--attach adds to them), Discord takes up to 10 in a message.
+ATTACHMENTS=()
+
+## Friendly names for user and role mentions, {{@oncall}} in a value becomes the mention:
+## MENTIONS=([oncall]="<@&1234>" [ahmet]="<@5678>")
+declare -A MENTIONS=()
+
+## Named targets (webhook URLs or telegram:, ntfy:, zulip:, email: targets), a name listing other
+## names is a group: TARGETS=([discord]="https://discord.com/api/webhooks/..." [everyone]="discord ntfy")
+## --to adds targets or names to TO, a message goes to all of them at once. Each target's provider
+## comes from its URL or prefix, PROVIDER only applies to WEBHOOK. URLs that don't give it away
+## name it in front: [hook]="generic+https://example.com/hook" [alerts]="ntfy+https://ntfy.example.com/alerts"
+declare -A TARGETS=()
+TO=()
+
+## Anything matching these is treated as a possible credential, conf.sh may override the list.
+SECRETPATTERNS=(
+	'discord(app)?\.com/api/webhooks/[0-9]+/[A-Za-z0-9_-]+'
+	'[MN][A-Za-z0-9_-]{23,25}\.[A-Za-z0-9_-]{6}\.[A-Za-z0-9_-]{27,}'
+	'gh[pousr]_[A-Za-z0-9]{36,}'
+	'xox[abposr]-[A-Za-z0-9-]{10,}'
+	'AKIA[0-9A-Z]{16}'
+	'sk-[A-Za-z0-9_-]{20,}'
+	'-----BEGIN [A-Z ]*PRIVATE KEY-----'
+	'[0-9]{8,10}:[A-Za-z0-9_-]{35}'
+	'hooks\.slack\.com/(services|workflows|triggers)/[A-Za-z0-9/_-]+'
+	'webhook\.office\.com/webhookb2/[A-Za-z0-9@/_-]+'
+	'outlook\.office\.com/webhook/[A-Za-z0-9@/_-]+'
+	'/hooks/[A-Za-z0-9]{20,}(/[A-Za-z0-9]+)?'
+	'[?&](token|sig)=[A-Za-z0-9_%-]{20,}'
+	'tk_[a-z0-9]{29}'
+	'AIza[0-9A-Za-z_-]{35}'
+)
+
+## Conf files can be kept gpg encrypted (e.g. conf.sh.gpg) when they hold sensitive values.
+hasconf() {
+	[[ -f "${1}" || -f "${1}.gpg" ]]
+}
+
+## Confs are sourced from inside this function: assign variables (TARGETS=([name]="...")),
+## a bare declare or declare -A in a conf would only make them local to it.
+loadconf() {
+	local decrypted
+
+	if [[ -f "${1}" ]] ; then
+		source "${1}"
+	elif decrypted="$(gpg --quiet --decrypt "${1}.gpg")" ; then
+		source <(printf '%s\n' "${decrypted}")
+	else
+		echo "can't decrypt conf \"${1}.gpg\"."
+		exit 1
+	fi
+}
+
+## Reads a --vars file without running it: KEY=value lines of a .env file or key = "value" lines of a flat
+## .toml, skipping comments, blank lines and [sections]. Every pair is a {{vars.key}}, and the ones named
+## like conf variables (REGION) are set as variables too, so templates and field defaults can use them.
+loadvars() {
+	local content line key value
+
+	if [[ -f "${1}" ]] ; then
+		content="$(< "${1}")"
+	elif ! content="$(gpg --quiet --decrypt "${1}.gpg")" ; then
+		echo "can't decrypt vars file \"${1}.gpg\"."
+		exit 1
+	fi
+
+	while IFS= read -r line ; do
+		line="${line#"${line%%[![:space:]]*}"}"
+		case "${line}" in
+			(""|"#"*|"["*)
+				continue
+			;;
+		esac
+		line="${line#export }"
+		key="${line%%=*}" value="${line#*=}"
+		key="${key%"${key##*[![:space:]]}"}"
+		value="${value#"${value%%[![:space:]]*}"}"
+		value="${value%"${value##*[![:space:]]}"}"
+		if [[ "${line}" != *"="* ]] || [[ ! "${key}" =~ ^[A-Za-z_][A-Za-z0-9_]*$ ]] ; then
+			echo "can't read \"${line}\" in vars file \"${1}\", use KEY=value."
+			exit 1
+		fi
+		if [[ "${value}" =~ ^\"(.*)\"$ ]] || [[ "${value}" =~ ^\'(.*)\'$ ]] ; then
+			value="${BASH_REMATCH[1]}"
+		fi
+		VARS["${key}"]="${value}"
+		if [[ "${key}" =~ ^[A-Z][A-Z0-9_]*$ ]] ; then
+			printf -v "${key}" '%s' "${value}"
+		fi
+	done <<< "${content}"
+}
+
+## Lets a conf build on another one, found like templates, for shared webhook/author/footer/color:
+## extends announcement/base
+extends() {
+	local dir ext file="" i
+
+	for dir in "" "${TEMPLATEDIR}/" "${SHAREDDIR}/" ; do
+		for ext in "" ".conf" ".conf.sh" ; do
+			if [[ -z "${file}" ]] && hasconf "${dir}${1}${ext}" ; then
+				file="$(readlink -f "${dir}${1}${ext}")"
+			fi
+		done
+	done
+
+	if [[ -z "${file}" ]] ; then
+		echo "can't find conf \"${1:-NULL}\" to extend."
+		exit 1
+	fi
+	for i in "${EXTENDING[@]}" ; do
+		if [[ "${i}" = "${file}" ]] ; then
+			echo "conf files extend each other in a cycle: ${EXTENDING[*]} ${file}"
+			exit 1
+		fi
+	done
+
+	EXTENDING+=("${file}")
+	loadconf "${file}"
+	unset "EXTENDING[-1]"
+}
+
+if hasconf "${CONFIG}" ; then
+	loadconf "${CONFIG}"
+fi
+
+if [[ -f "${BASEDIR}/bin/discord/discord.sh" ]] ; then
+	export DISCORDSH="${BASEDIR}/bin/discord/discord.sh"
+else
+	echo -e "requirement discord.sh not found. You can try:\n\t- git submodule init\n\t- git submodule update\nor:\n\t- git clone https://github.com/fieu/discord.sh.git \"${BASEDIR}/bin/discord\""
+	exit 1
+fi
+
+## Scratch space for downloads and archives, removed on exit.
+export WORKDIR="$(mktemp -d)"
+trap 'rm -rf "${WORKDIR}"' EXIT
+
+## Prints a saved snippet so templates can insert it into any field:
+## --footer "$(snippet signature)"
+snippet() {
+	if [[ -f "${SNIPPETDIR}/${1}" ]] ; then
+		cat "${SNIPPETDIR}/${1}"
+	else
+		echo "can't find snippet \"${1:-NULL}\" in \"${SNIPPETDIR}\"." >&2
+		return 1
+	fi
+}
+
+confirm() {
+	local answer
+	[[ "${ASSUMEYES}" = "yes" ]] && return 0
+	if [[ ! -t 0 ]] ; then
+		echo "${1} not asking without a terminal, pass --yes to confirm."
+		return 1
+	fi
+	read -r -p "${1} [y/N] " answer
+	[[ "${answer,,}" =~ ^(y|yes)$ ]]
+}
+
+## Prints everything in the input that looks like a credential.
+findsecrets() {
+	local IFS="|"
+	grep -ohIE -- "${SECRETPATTERNS[*]}" "${@}" || true
+}
+
+## Matches the input against BLOCKLIST and prints the offending words.
+findblocked() {
+	local IFS="|"
+	(( "${#BLOCKLIST[@]}" > 0 )) || return 0
+	grep -oiE -- "${BLOCKLIST[*]}" || true
+}
+
+## Asks before going over THROTTLE for the given webhook, forgets sends older than the window.
+throttle() {
+	local file="${STATEDIR}/throttle/$(md5sum <<< "${1}" | cut -d " " -f 1)" now="$(date +%s)" i recent=()
+
+	[[ -n "${THROTTLE}" ]] || return 0
+	if [[ -f "${file}" ]] ; then
+		while read -r i ; do
+			(( now - i < ${THROTTLE#*/} )) && recent+=("${i}")
+		done < "${file}"
+	fi
+
+	if (( "${#recent[@]}" >= ${THROTTLE%/*} )) ; then
+		echo "${#recent[@]} messages were already sent to this webhook in the last ${THROTTLE#*/} seconds."
+		confirm "send it anyway?" || exit 1
+	fi
+
+	mkdir -p "${file%/*}"
+	printf '%s' "${recent[@]/%/$'\n'}" > "${file}"
+}
+
+## Remembers a send for throttle.
+sent() {
+	[[ -n "${THROTTLE}" ]] || return 0
+	date +%s >> "${STATEDIR}/throttle/$(md5sum <<< "${1}" | cut -d " " -f 1)"
+}
+
+## Every sent message is kept as "date<TAB>template<TAB>discord.sh arguments[<TAB>message link]".
+remember() {
+	mkdir -p "${HISTORYFILE%/*}"
+	( umask 077 ; printf '%s\t%s\t%s%s\n' "$(date +%FT%T%z)" "${TEMPLATE:-NULL}" "$(printf '%q ' "${@}")" "${MESSAGEURL:+$'\t'"${MESSAGEURL}"}" >> "${HISTORYFILE}" )
+}
+
+## With ARCHIVEDIR set (e.g. in a template's conf) every sent message is appended to a markdown
+## file per target in it: discord-<webhook id>.md, or <provider>-<hash of the target>.md for others.
+archive() {
+	local i title="" description="" file fields=()
+	local -a args=("${@:2}")
+
+	[[ -n "${ARCHIVEDIR}" ]] || return 0
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		case "${args[i]}" in
+			("--title") title="${args[i + 1]}" ;;
+			("--description") description="${args[i + 1]}" ;;
+			("--field") fields+=("${args[i + 1]}") ;;
+		esac
+	done
+
+	if [[ "$(provider "${1}")" = "discord" ]] && [[ "${1}" =~ /webhooks/([0-9]+)/ ]] ; then
+		file="${ARCHIVEDIR}/discord-${BASH_REMATCH[1]}.md"
+	else
+		file="${ARCHIVEDIR}/$(provider "${1}")-$(md5sum <<< "${1%%\?*}" | cut -c 1-12).md"
+	fi
+
+	mkdir -p "${ARCHIVEDIR}"
+	{
+		echo "## ${title:-"${TEMPLATE##*/}"}"
+		echo
+		echo "_$(date "+%F %T") by $(id -un), ${TEMPLATE##*/}_"
+		echo
+		if [[ -n "${description}" ]] ; then
+			printf '%s\n\n' "${description//\\n/$'\n'}"
+		fi
+		if (( "${#fields[@]}" > 0 )) ; then
+			for i in "${fields[@]}" ; do
+				printf -- '- **%s**: %s\n' "${i%%;*}" "$(cut -d ";" -f 2 <<< "${i//\\n/ }")"
+			done
+			echo
+		fi
+	} >> "${file}"
+}
+
+## Removes the variables that carry credentials (webhooks, tokens, passwords, targets) from a conf
+## or text file, whole arrays, appends (+=) and single elements (NAME[key]=) included,
+## and blanks out anything else SECRETPATTERNS matches.
+scrub() {
+	local IFS="|"
+
+	grep -qI "" "${1}" || return 0
+	awk -v names="^[[:space:]]*((export|readonly|declare[[:space:]]+-[[:alpha:]]+)[[:space:]]+)?(WEBHOOK[A-Z0-9_]*|INTERACTION|TARGETS|HEADERS|SMTPUSER|ZULIPKEY|ZULIPBOT|[A-Z0-9_]*(TOKEN|SECRET|PASSWORD|APIKEY)[A-Z0-9_]*)([[][^]]*[]])?[+]?=" '
+		skip && /\)/ { skip = 0 ; next }
+		skip { next }
+		$0 ~ names { if ($0 ~ /=\(/ && $0 !~ /\)/) skip = 1 ; next }
+		{ print }
+	' "${1}" | sed -E "s"$'\x01'"${SECRETPATTERNS[*]}"$'\x01'"[redacted]"$'\x01'"g" > "${1}.scrubbed"
+	mv "${1}.scrubbed" "${1}"
+}
+
+## Prints history entries matching all of the given words (date, template or any text), numbered for --resend,
+## sent between SINCE and UNTIL (YYYY-MM-DD, both included) when they're set.
+searchhistory() {
+	local n=0 i line word title description link
+	local -a args
+
+	[[ -f "${HISTORYFILE}" ]] || return 0
+	while IFS= read -r line ; do
+		(( ++n ))
+		if [[ -n "${SINCE}" ]] && [[ "${line:0:10}" < "${SINCE}" ]] ; then
+			continue
+		fi
+		if [[ -n "${UNTIL}" ]] && [[ "${line:0:10}" > "${UNTIL}" ]] ; then
+			continue
+		fi
+		for word in "${@}" ; do
+			[[ "${line,,}" = *"${word,,}"* ]] || continue 2
+		done
+		link="${line#*$'\t'*$'\t'}"
+		eval "args=(${link%%$'\t'*})"
+		link="$(cut -s -f 4 <<< "${line}")"
+		title="" description=""
+		for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+			case "${args[i]}" in
+				("--title") title="${args[i + 1]}" ;;
+				("--description") description="${args[i + 1]%%\\n*}" ;;
+			esac
+		done
+		echo -e "${n}\t${line%%$'\t'*}\t$(cut -f 2 <<< "${line}")\t${title:-"-"}\t${description:0:60}${link:+$'\t'"${link}"}"
+	done < "${HISTORYFILE}"
+}
+
+## Prints the text as it is, or JSON-escaped (without the quotes) when the format is "json".
+escaped() {
+	local text="${2}"
+
+	if [[ "${1}" = "json" ]] ; then
+		text="$(printf '%s' "${text}" | jq -R -s .)"
+		text="${text:1:-1}"
+	fi
+
+	printf '%s' "${text}"
+}
+
+## Fills {{date}}, {{time}}, {{hostname}}, {{user}}, {{field:NAME}} (any variable), {{vars.name}} (--vars files)
+## and {{@name}} (MENTIONS) placeholders,
+## with "json" as the second argument the values are escaped to sit inside JSON strings.
+expand() {
+	local value="${1}" format="${2}" name
+
+	value="${value//"{{date}}"/"$(escaped "${format}" "$(date +%F)")"}"
+	value="${value//"{{time}}"/"$(escaped "${format}" "$(date +%R)")"}"
+	value="${value//"{{hostname}}"/"$(escaped "${format}" "${HOSTNAME}")"}"
+	value="${value//"{{user}}"/"$(escaped "${format}" "${USER:-"$(id -un)"}")"}"
+	for name in $(grep -oE "\{\{field:[A-Za-z_][A-Za-z0-9_]*\}\}" <<< "${value}" | sort -u) ; do
+		name="${name#"{{field:"}"
+		name="${name%"}}"}"
+		value="${value//"{{field:${name}}}"/"$(escaped "${format}" "${!name}")"}"
+	done
+	for name in $(grep -oE "\{\{vars\.[A-Za-z_][A-Za-z0-9_]*\}\}" <<< "${value}" | sort -u) ; do
+		name="${name#"{{vars."}"
+		name="${name%"}}"}"
+		if [[ -z "${VARS[${name}]+set}" ]] ; then
+			echo "can't find \"${name}\" in the --vars files." >&2
+			return 1
+		fi
+		value="${value//"{{vars.${name}}}"/"$(escaped "${format}" "${VARS[${name}]}")"}"
+	done
+	for name in $(grep -oE "\{\{@[A-Za-z0-9_.-]+\}\}" <<< "${value}" | sort -u) ; do
+		name="${name#"{{@"}"
+		name="${name%"}}"}"
+		if [[ -z "${MENTIONS[${name}]}" ]] ; then
+			echo "can't find mention \"${name}\" in MENTIONS." >&2
+			return 1
+		fi
+		value="${value//"{{@${name}}}"/"$(escaped "${format}" "${MENTIONS[${name}]}")"}"
+	done
+
+	printf '%s' "${value}"
+}
+
+## Accepts a field's inline part ("name;value;inline") as yes/no, on/off or 1/0 too and prints
+## the field the way discord.sh wants it, with true or false.
+inline() {
+	local flag="${1##*;}"
+
+	if [[ "${1}" != *";"*";"* ]] ; then
+		echo "${1}"
+		return 0
+	fi
+
+	case "${flag,,}" in
+		("true"|"yes"|"on"|"1"|"inline") flag="true" ;;
+		("false"|"no"|"off"|"0"|"") flag="false" ;;
+		(*)
+			echo "\"${flag}\" is not an inline flag like true or false." >&2
+			return 1
+		;;
+	esac
+
+	echo "${1%;*};${flag}"
+}
+
+## Accepts colors as #2e954d, 2e954d or 0x2e954d and prints them the way discord.sh wants them.
+hexcolor() {
+	local color="${1#"#"}"
+
+	color="${color#0x}"
+	if [[ ! "${color}" =~ ^[0-9A-Fa-f]{6}$ ]] ; then
+		echo "\"${1}\" is not a hex color like #2e954d." >&2
+		return 1
+	fi
+
+	echo "0x${color,,}"
+}
+
+## Counts the characters of discord.sh arguments that Discord holds against the 6000 of an embed.
+embedsize() {
+	local i key value name total=0
+	local -a args=("${@}")
+
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		key="${args[i]}"
+		if [[ "${key}" = --*=* ]] ; then
+			value="${key#*=}" key="${key%%=*}"
+		else
+			value="${args[i + 1]}"
+		fi
+		value="${value//\\n/$'\n'}"
+
+		case "${key}" in
+			("--title"|"--description"|"--footer"|"--author")
+				total="$(( total + ${#value} ))"
+			;;
+			("--field")
+				name="${value%%;*}"
+				value="$(cut -d ";" -f 2 <<< "${value}")"
+				total="$(( total + ${#name} + ${#value} ))"
+			;;
+		esac
+	done
+	echo "${total}"
+}
+
+## Checks discord.sh arguments against Discord's limits and URL fields, prints "option<TAB>problem" for each broken one.
+limits() {
+	local i key value name fields=0
+	local -a args=("${@}")
+	local -A max=(
+		["--title"]=256 ["--description"]=4096 ["--footer"]=2048
+		["--author"]=256 ["--username"]=80 ["--text"]=2000
+	)
+
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		key="${args[i]}"
+		if [[ "${key}" = --*=* ]] ; then
+			value="${key#*=}" key="${key%%=*}"
+		else
+			value="${args[i + 1]}"
+		fi
+		value="${value//\\n/$'\n'}"
+
+		case "${key}" in
+			("--title"|"--description"|"--footer"|"--author"|"--username"|"--text")
+				if (( "${#value}" > max[${key}] )) ; then
+					echo -e "${key}\t${key#--} is ${#value} characters, Discord allows ${max[${key}]}"
+				fi
+			;;
+			("--url"|"--author-url"|"--author-icon"|"--avatar"|"--thumbnail"|"--image"|"--footer-icon")
+				if [[ -n "${value}" ]] && [[ ! "${value}" =~ ^(https?|attachment)://[^[:space:]]+$ ]] ; then
+					echo -e "${key}\t${key#--} \"${value:0:20}\" isn't a http(s):// or attachment:// URL"
+				fi
+			;;
+			("--field")
+				fields="$(( fields + 1 ))"
+				name="${value%%;*}"
+				value="$(cut -d ";" -f 2 <<< "${value}")"
+				if (( "${#name}" > 256 )) ; then
+					echo -e "${key}\tfield name \"${name:0:20}...\" is ${#name} characters, Discord allows 256"
+				fi
+				if (( "${#value}" > 1024 )) ; then
+					echo -e "${key}\tfield \"${name}\" value is ${#value} characters, Discord allows 1024"
+				fi
+			;;
+		esac
+	done
+
+	if (( fields > 25 )) ; then
+		echo -e "--field\t${fields} fields, Discord allows 25"
+	fi
+}
+
+## Checks a built Discord payload on stdin against Discord's limits, counting the total across all of its embeds, prints "option<TAB>problem" for each broken one.
+payloadlimits() {
+	jq -r '
+		def over($key; $what; $max): select(length > $max) | "\($key)\t\($what) is \(length) characters, Discord allows \($max)";
+		def link($key; $what): select(. != null and (test("^(https?|attachment)://[^\\s]+$") | not)) | "\($key)\t\($what) \"\(.[:20])\" isn'"'"'t a http(s):// or attachment:// URL";
+		(.embeds // []) as $embeds
+		| (.content // "" | over("--text"; "text"; 2000)),
+		(.username // "" | over("--username"; "username"; 80)),
+		(.avatar_url | link("--avatar"; "avatar")),
+		(if ($embeds | length) > 10 then "embed\t\($embeds | length) embeds, Discord allows 10" else empty end),
+		($embeds | to_entries[] | (if ($embeds | length) > 1 then "embed \(.key + 1): " else "" end) as $n | .value
+			| (.title // "" | over("--title"; "\($n)title"; 256)),
+			(.description // "" | over("--description"; "\($n)description"; 4096)),
+			(.footer.text // "" | over("--footer"; "\($n)footer"; 2048)),
+			(.author.name // "" | over("--author"; "\($n)author"; 256)),
+			(.url | link("--url"; "\($n)url")),
+			(.author.url | link("--author-url"; "\($n)author-url")),
+			(.author.icon_url | link("--author-icon"; "\($n)author-icon")),
+			(.thumbnail.url | link("--thumbnail"; "\($n)thumbnail")),
+			(.image.url | link("--image"; "\($n)image")),
+			(.footer.icon_url | link("--footer-icon"; "\($n)footer-icon")),
+			(.fields // [] | length | select(. > 25) | "--field\t\($n)\(.) fields, Discord allows 25"),
+			(.fields // [] | .[] | . as $field
+				| (.name // "" | over("--field"; "\($n)field name \"\(.[:20])...\""; 256)),
+				(.value // "" | over("--field"; "\($n)field \"\($field.name)\" value"; 1024)))),
+		([$embeds[] | .title, .description, .footer.text, .author.name, (.fields // [] | .[] | .name, .value) | . // "" | length] | add // 0
+			| select(. > 6000) | "embed\tthe embeds have \(.) characters in total, Discord allows 6000")
+	'
+}
+
+## Cuts a description (lines joined with "\n") into parts of at most the given length, breaking
+## between lines where it can, one part per line of output.
+parts() {
+	local max="${2}" part="" line
+	local -a lines
+
+	mapfile -t lines <<< "${1//\\n/$'\n'}"
+	for line in "${lines[@]}" ; do
+		while (( "${#line}" > max )) ; do
+			if [[ -n "${part}" ]] ; then
+				echo "${part}"
+				part=""
+			fi
+			echo "${line:0:max}"
+			line="${line:max}"
+		done
+		if [[ -z "${part}" ]] ; then
+			part="${line}"
+		elif (( "${#part}" + 2 + "${#line}" > max )) ; then
+			echo "${part}"
+			part="${line}"
+		else
+			part+="\\n${line}"
+		fi
+	done
+	if [[ -n "${part}" ]] ; then
+		echo "${part}"
+	fi
+}
+
+## Points the webhook URL at THREAD (a thread or forum post id) when one is set, THREADNAME
+## instead starts a new post in a forum channel.
+threaded() {
+	if [[ -z "${THREAD}" ]] ; then
+		echo "${1}"
+	elif [[ "${1}" = *"?"* ]] ; then
+		echo "${1}&thread_id=${THREAD}"
+	else
+		echo "${1}?thread_id=${THREAD}"
+	fi
+}
+
+## Asks Discord who the webhook posts as and where, prints name, avatar, channel and guild one per line.
+webhookinfo() {
+	if ! command -v jq &> /dev/null ; then
+		echo "requirement jq not found." >&2
+		exit 1
+	fi
+
+	local response
+
+	response="$(request "$(address "${1%%\?*}")")" || return 1
+	jq -r '
+		"name: \(.name // "-")",
+		"avatar: \(if .avatar then "https://cdn.discordapp.com/avatars/\(.id)/\(.avatar).png" else "-" end)",
+		"channel: \(.channel_id // "-")",
+		"guild: \(.guild_id // "-")"
+	' <<< "${response}"
+}
+
+## Prints which service the webhook belongs to: the "<provider>+" prefix of the URL when it has one,
+## PROVIDER when it's set, otherwise guessed from the URL. A URL it can't place is an error.
+provider() {
+	local host
+
+	if [[ "${1}" =~ ^(discord|teams|mattermost|rocketchat|googlechat|ntfy|generic)\+https?:// ]] ; then
+		echo "${BASH_REMATCH[1]}"
+		return 0
+	fi
+	if [[ -n "${PROVIDER}" ]] ; then
+		echo "${PROVIDER,,}"
+		return 0
+	fi
+
+	case "${1}" in
+		(*".webhook.office.com/"*|*"outlook.office.com/webhook"*)
+			echo "teams"
+		;;
+		(*"/hooks/"*"/"*)
+			echo "rocketchat"
+		;;
+		(*"/hooks/"*)
+			echo "mattermost"
+		;;
+		("zulip:"*)
+			echo "zulip"
+		;;
+		("email:"*)
+			echo "email"
+		;;
+		("telegram:"*)
+			echo "telegram"
+		;;
+		(*"chat.googleapis.com/"*)
+			echo "googlechat"
+		;;
+		("ntfy:"*|*"://ntfy.sh/"*)
+			echo "ntfy"
+		;;
+		(*"://discord.com/api/webhooks/"*|*"://"*".discord.com/api/webhooks/"*|*"://discordapp.com/api/webhooks/"*)
+			echo "discord"
+		;;
+		(*)
+			host="${1#*://}"
+			echo "can't tell the provider of webhook \"${host%%/*}\", set PROVIDER or prefix it like generic+https://..." >&2
+			return 1
+		;;
+	esac
+}
+
+## Prints the webhook without its "<provider>+" prefix.
+address() {
+	if [[ "${1}" =~ ^[a-z]+\+(https?://.*)$ ]] ; then
+		echo "${BASH_REMATCH[1]}"
+	else
+		echo "${1}"
+	fi
+}
+
+## Turns FLAGS (silent, suppress-embeds) into Discord's message flags bitfield.
+flags() {
+	local flag bits=0
+
+	for flag in ${FLAGS} ; do
+		case "${flag,,}" in
+			("silent"|"suppress-notifications")
+				bits="$(( bits | 4096 ))"
+			;;
+			("suppress-embeds")
+				bits="$(( bits | 4 ))"
+			;;
+			(*)
+				echo "can't find message flag \"${flag}\", use silent or suppress-embeds." >&2
+				exit 1
+			;;
+		esac
+	done
+	echo "${bits}"
+}
+
+## Prints the JSON the webhook's provider takes: discord.sh's own payload for Discord, converted
+## with jq for the others. The generic provider sends BODY with its {{placeholders}} filled in
+## (or the Discord payload as it is), sent with METHOD.
+payload() {
+	local webhook="${1}" json subject bits
+	local defs='def hex: [range(5; -1; -1) as $i | (. / pow(16; $i) | floor) % 16 | "0123456789abcdef"[.:. + 1]] | join("");'
+	shift
+
+	json="$("${DISCORDSH}" --dry-run "${@}")" || return 1
+
+	case "$(provider "${webhook}")" in
+		("discord")
+			bits="$(flags)" || return 1
+			jq --argjson flags "${bits}" --arg thread "${THREADNAME}" --argjson embeds "$(printf '%s\n' "${EMBEDS[@]}" | jq -s .)" '
+				if $embeds != [] then .embeds = (.embeds // []) + $embeds else . end
+				| if $flags > 0 then .flags = $flags else . end
+				| if $thread != "" then .thread_name = $thread else . end
+			' <<< "${json}"
+		;;
+		("teams")
+			jq "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					"@type": "MessageCard",
+					"@context": "https://schema.org/extensions",
+					summary: ($embed.title // .content // "message"),
+					themeColor: (if $embed.color then ($embed.color | hex) else null end),
+					title: $embed.title,
+					text: ([.content, $embed.description] | map(select(. != null)) | join("\n\n")),
+					sections: [{
+						activityTitle: $embed.author.name,
+						activityImage: ($embed.author.icon_url // $embed.thumbnail.url),
+						facts: [$embed.fields // [] | .[] | {name, value}],
+						images: [$embed.image.url // empty | {image: .}],
+						text: $embed.footer.text
+					} | with_entries(select(.value != null and .value != []))] | map(select(. != {})),
+					potentialAction: [$embed.url // empty | {"@type": "OpenUri", name: "Open", targets: [{os: "default", uri: .}]}]
+				} | with_entries(select(.value != null and .value != [] and .value != ""))
+			' <<< "${json}"
+		;;
+		("mattermost")
+			jq "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					username: .username,
+					icon_url: .avatar_url,
+					text: .content,
+					attachments: [$embed | select(. != {}) | {
+						fallback: (.title // .description // "message"),
+						color: (if .color then "#\(.color | hex)" else null end),
+						author_name: .author.name,
+						author_link: .author.url,
+						author_icon: .author.icon_url,
+						title: .title,
+						title_link: .url,
+						text: .description,
+						fields: [.fields // [] | .[] | {title: .name, value, short: (.inline // false)}],
+						image_url: .image.url,
+						thumb_url: .thumbnail.url,
+						footer: .footer.text,
+						footer_icon: .footer.icon_url
+					} | with_entries(select(.value != null and .value != []))]
+				} | with_entries(select(.value != null and .value != []))
+			' <<< "${json}"
+		;;
+		("telegram")
+			jq --arg chat "${webhook##*/}" "${defs}"'
+				def html: gsub("&"; "&amp;") | gsub("<"; "&lt;") | gsub(">"; "&gt;")
+					| gsub("\\*\\*(?<t>[^*]+)\\*\\*"; "<b>\(.t)</b>") | gsub("__(?<t>[^_]+)__"; "<u>\(.t)</u>")
+					| gsub("\\*(?<t>[^*]+)\\*"; "<i>\(.t)</i>") | gsub("~~(?<t>[^~]+)~~"; "<s>\(.t)</s>")
+					| gsub("\\|\\|(?<t>[^|]+)\\|\\|"; "<tg-spoiler>\(.t)</tg-spoiler>") | gsub("`(?<t>[^`]+)`"; "<code>\(.t)</code>");
+				(.embeds[0] // {}) as $embed | {
+					chat_id: $chat,
+					parse_mode: "HTML",
+					text: ([
+						(.content // empty | html),
+						($embed.author.name // empty | html | "<i>\(.)</i>"),
+						($embed.title // empty | html | "<b>\(.)</b>" | if $embed.url then "<a href=\"\($embed.url | html)\">\(.)</a>" else . end),
+						($embed.description // empty | html),
+						($embed.fields // [] | .[] | "<b>\(.name | html)</b>: \(.value | html)"),
+						($embed.image.url // empty | html),
+						([$embed.footer.text // empty, $embed.timestamp // empty] | select(length > 0) | map(html) | join(" • ") | "<i>\(.)</i>")
+					] | join("\n"))
+				}
+			' <<< "${json}"
+		;;
+		("googlechat")
+			jq "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					text: .content,
+					cardsV2: [$embed | select(. != {}) | {
+						cardId: "message",
+						card: {
+							header: ({
+								title: (.title // .author.name),
+								subtitle: (if .title then .author.name else null end),
+								imageUrl: (.thumbnail.url // .author.icon_url)
+							} | with_entries(select(.value != null))),
+							sections: [{
+								widgets: [
+									(.description // empty | {textParagraph: {text: .}}),
+									(.fields // [] | .[] | {decoratedText: {topLabel: .name, text: .value, wrapText: true}}),
+									(.image.url // empty | {image: {imageUrl: .}}),
+									(.url // empty | {buttonList: {buttons: [{text: "Open", onClick: {openLink: {url: .}}}]}}),
+									([.footer.text // empty, .timestamp // empty] | select(length > 0) | {textParagraph: {text: "<i>\(join(" • "))</i>"}})
+								]
+							}]
+						} | with_entries(select(.value != {}))
+					}]
+				} | with_entries(select(.value != null and .value != []))
+			' <<< "${json}"
+		;;
+		("rocketchat")
+			jq "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					alias: .username,
+					avatar: .avatar_url,
+					text: .content,
+					attachments: [$embed | select(. != {}) | {
+						color: (if .color then "#\(.color | hex)" else null end),
+						author_name: .author.name,
+						author_link: .author.url,
+						author_icon: .author.icon_url,
+						title: .title,
+						title_link: .url,
+						text: ([.description // empty, .footer.text // empty] | join("\n\n")),
+						fields: [.fields // [] | .[] | {title: .name, value, short: (.inline // false)}],
+						image_url: .image.url,
+						thumb_url: .thumbnail.url
+					} | with_entries(select(.value != null and .value != [] and .value != ""))]
+				} | with_entries(select(.value != null and .value != []))
+			' <<< "${json}"
+		;;
+		("zulip")
+			jq --arg stream "${webhook#zulip:}" --arg topic "${ZULIPTOPIC}" "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					type: "stream",
+					to: $stream,
+					topic: (if $topic != "" then $topic else ($embed.title // .username // "(no topic)") end),
+					content: ([
+						(.content // empty),
+						($embed.description // empty),
+						($embed.fields // [] | .[] | "**\(.name)**: \(.value)"),
+						($embed.image.url // empty | "[image](\(.))"),
+						($embed.url // empty),
+						($embed.footer.text // empty | "*\(.)*")
+					] | join("\n\n"))
+				}
+			' <<< "${json}"
+		;;
+		("email")
+			subject="$(jq -r '(.embeds[0].title // .content // "message") | split("\n")[0]' <<< "${json}")"
+			printf 'From: %s\nTo: %s\nSubject: =?UTF-8?B?%s?=\nDate: %s\nMIME-Version: 1.0\nContent-Type: text/html; charset=UTF-8\n\n' \
+				"${MAILFROM}" "${webhook#email:}" "$(printf '%s' "${subject}" | base64 -w 0)" "$(date -R)"
+			jq -r "${defs}"'
+				def text: @html | gsub("\n"; "<br>");
+				(.embeds[0] // {}) as $embed | [
+					"<div style=\"font-family: sans-serif;\">",
+					(.content // empty | "<p>\(text)</p>"),
+					"<table style=\"border-left: 4px solid #\($embed.color // 0 | hex); padding: 8px 12px; border-collapse: collapse;\">",
+					($embed.author.name // empty | "<tr><td colspan=\"2\" style=\"color: #555;\">\(text)</td></tr>"),
+					($embed.title // empty | text | "<tr><td colspan=\"2\" style=\"font-size: 1.2em;\"><b>\(if $embed.url then "<a href=\"\($embed.url | @html)\">\(.)</a>" else . end)</b></td></tr>"),
+					($embed.description // empty | "<tr><td colspan=\"2\">\(text)</td></tr>"),
+					($embed.fields // [] | .[] | "<tr><td style=\"padding-right: 12px; vertical-align: top;\"><b>\(.name | text)</b></td><td>\(.value | text)</td></tr>"),
+					($embed.image.url // empty | "<tr><td colspan=\"2\"><img src=\"\(@html)\" style=\"max-width: 100%;\"></td></tr>"),
+					([$embed.footer.text // empty, $embed.timestamp // empty] | select(length > 0) | "<tr><td colspan=\"2\" style=\"color: #777; font-size: 0.8em;\">\(join(" • ") | text)</td></tr>"),
+					"</table>",
+					"</div>"
+				] | join("\n")
+			' <<< "${json}"
+		;;
+		("ntfy")
+			jq --arg topic "${webhook##*[:/]}" --arg priority "${PRIORITY}" "${defs}"'
+				(.embeds[0] // {}) as $embed | {
+					topic: $topic,
+					title: ($embed.title // .username),
+					message: ([
+						(.content // empty),
+						($embed.description // empty),
+						($embed.fields // [] | .[] | "**\(.name)**: \(.value)"),
+						($embed.footer.text // empty)
+					] | join("\n\n")),
+					markdown: true,
+					priority: ({"min": 1, "low": 2, "default": 3, "high": 4, "urgent": 5, "max": 5}[$priority] // ($priority | tonumber? // null)),
+					click: $embed.url,
+					attach: $embed.image.url,
+					icon: ($embed.thumbnail.url // .avatar_url)
+				} | with_entries(select(.value != null and .value != ""))
+			' <<< "${json}"
+		;;
+		("generic")
+			## a JSON body (the default Content-Type) gets its values escaped and has to stay valid JSON
+			if [[ -n "${BODY}" ]] && [[ "${HEADERS[*],,}" = *"content-type:"* ]] && [[ "${HEADERS[*],,}" != *"content-type:"*"json"* ]] ; then
+				expand "${BODY}"
+			elif [[ -n "${BODY}" ]] ; then
+				json="$(expand "${BODY}" json)" || return 1
+				if ! jq -e . > /dev/null 2>&1 <<< "${json}" ; then
+					echo "BODY isn't valid JSON once its placeholders are filled in." >&2
+					return 1
+				fi
+				echo "${json}"
+			else
+				echo "${json}"
+			fi
+		;;
+		(*)
+			echo "unknown provider \"$(provider "${webhook}")\"." >&2
+			return 1
+		;;
+	esac
+}
+
+## Prints the URL a provider's payload is POSTed to, Discord's with THREAD and ?wait=true to get the message back,
+## telegram:<bot token>/<chat id> targets go to the Bot API
+## ntfy topics (ntfy:<topic> or <server>/<topic>) to the server's JSON endpoint and zulip:<stream>
+## to ZULIPSITE's messages API.
+endpoint() {
+	case "$(provider "${1}")" in
+		("telegram")
+			local target="${1#telegram:}"
+			echo "https://api.telegram.org/bot${target%/*}/sendMessage"
+		;;
+		("zulip")
+			echo "${ZULIPSITE%/}/api/v1/messages"
+		;;
+		("discord")
+			local url
+			url="$(threaded "$(address "${1}")")"
+			if [[ "${url}" = *"?"* ]] ; then
+				echo "${url}&wait=true"
+			else
+				echo "${url}?wait=true"
+			fi
+		;;
+		("ntfy")
+			if [[ "${1}" = "ntfy:"* ]] ; then
+				echo "${NTFYSERVER:-"https://ntfy.sh"}"
+			else
+				address "${1%/*}"
+			fi
+		;;
+		(*)
+			address "${1}"
+		;;
+	esac
+}
+
+## With EDITJSON="yes" (--edit-json) the payload opens in the editor before it is sent, for
+## anything the options don't cover. It is shown again highlighted and has to stay valid JSON.
+editjson() {
+	local file
+
+	if [[ "${EDITJSON}" != "yes" ]] || ! jq empty <<< "${1}" 2> /dev/null ; then
+		echo "${1}"
+		return 0
+	fi
+
+	file="$(mktemp --suffix=.json)"
+	jq . <<< "${1}" > "${file}"
+	while true ; do
+		${VISUAL:-${EDITOR:-vi}} "${file}" < /dev/tty > /dev/tty
+		if jq -C . "${file}" > /dev/tty 2> /dev/null ; then
+			break
+		fi
+		echo "the payload isn't valid JSON." > /dev/tty
+		if ! confirm "edit it again?" < /dev/tty > /dev/tty ; then
+			rm -f "${file}"
+			exit 1
+		fi
+	done
+	jq -c . "${file}"
+	rm -f "${file}"
+}
+
+## Prints a curl config line, read with -K so secrets (credentials, tokens in URLs) stay out
+## of the process list: curlrc url "https://api.telegram.org/bot<token>/sendMessage"
+curlrc() {
+	local value="${2//\\/\\\\}"
+
+	printf '%s = "%s"\n' "${1}" "${value//\"/\\\"}"
+}
+
+## Prints the curl config for a user and password, nothing without a user.
+credentials() {
+	[[ -n "${1}" ]] || return 0
+	curlrc user "${1}:${2}"
+}
+
+## Sets MESSAGEURL to the jump link of a message Discord returned for ?wait=true and prints it.
+sentmessage() {
+	local guild
+
+	guild="$(jq -r '.guild_id // empty' <<< "${2}")"
+	if [[ -z "${guild}" ]] ; then
+		guild="$(request "$(address "${1%%\?*}")" | jq -r '.guild_id // "@me"')" || guild="@me"
+	fi
+	MESSAGEURL="$(jq -r --arg guild "${guild}" '"https://discord.com/channels/\($guild)/\(.channel_id)/\(.id)"' <<< "${2}")"
+	echo "sent message $(jq -r '.id' <<< "${2}"): ${MESSAGEURL}"
+}
+
+## Runs curl with HEADERS and USERAGENT, every HTTP request goes through here so relays and
+## proxies in between see them.
+request() {
+	local header
+	local -a options=()
+
+	if [[ -n "${USERAGENT}" ]] ; then
+		options+=("-A" "${USERAGENT}")
+	fi
+	for header in "${HEADERS[@]}" ; do
+		options+=("-H" "${header}")
+	done
+	curl -fsS "${options[@]}" "${@}"
+}
+
+## Sends the message as a POST of the provider's payload, Discord's with ?wait=true and its
+## attachments as multipart files, so the created message can be linked.
+deliver() {
+	local webhook="${1}" json header url response i
+	local -a options=("-X" "POST") recipients=() args=() files=()
+	shift
+	MESSAGEURL=""
+
+	if [[ "${HEADERS[*],,}" != *"content-type:"* ]] ; then
+		options+=("-H" "Content-Type: application/json")
+	fi
+
+	if [[ "$(provider "${webhook}")" = "discord" ]] ; then
+		for (( i = 1 ; i <= ${#} ; i++ )) ; do
+			if [[ "${!i}" = "--file" ]] ; then
+				(( ++i ))
+				files+=("-F" "files[$(( ${#files[@]} / 2 ))]=@\"${!i}\"")
+			else
+				args+=("${!i}")
+			fi
+		done
+		url="$(endpoint "${webhook}")"
+		json="$(payload "${webhook}" "${args[@]}")" || exit 1
+		json="$(editjson "${json}")" || exit 1
+		if (( "${#files[@]}" > 0 )) ; then
+			response="$(request -F "payload_json=<-;type=application/json" "${files[@]}" -K <(curlrc url "${url}") <<< "${json}")" || return 1
+		else
+			response="$(request "${options[@]}" --data-binary @- -K <(curlrc url "${url}") <<< "${json}")" || return 1
+		fi
+		sentmessage "${webhook}" "${response}"
+		return 0
+	fi
+
+	## Zulip's API takes form fields and the bot's credentials instead of JSON.
+	if [[ "$(provider "${webhook}")" = "zulip" ]] ; then
+		json="$(payload "${webhook}" "${@}")" || exit 1
+		json="$(editjson "${json}")" || exit 1
+		jq -r 'to_entries | map("\(.key)=\(.value | @uri)") | join("&")' <<< "${json}" \
+			| request -K <(credentials "${ZULIPBOT}" "${ZULIPKEY}") --data-binary @- "$(endpoint "${webhook}")" > /dev/null || return 1
+		return 0
+	fi
+
+	## Mail goes to every address of email:<address>[,<address>...] through SMTPURL.
+	if [[ "$(provider "${webhook}")" = "email" ]] ; then
+		options=("--ssl" "--crlf" "--mail-from" "${MAILFROM}")
+		if [[ -n "${SMTPUSER}" ]] ; then
+			options=("--ssl-reqd" "--crlf" "--mail-from" "${MAILFROM}")
+		fi
+		IFS="," read -r -a recipients <<< "${webhook#email:}"
+		for header in "${recipients[@]}" ; do
+			options+=("--mail-rcpt" "${header}")
+		done
+		json="$(payload "${webhook}" "${@}")" || exit 1
+		curl -fsS "${options[@]}" -K <(credentials "${SMTPUSER}" "${SMTPPASSWORD}") --upload-file - "${SMTPURL}" <<< "${json}" || return 1
+		return 0
+	fi
+
+	if [[ "$(provider "${webhook}")" = "generic" ]] ; then
+		options[1]="${METHOD:-"POST"}"
+	fi
+
+	json="$(payload "${webhook}" "${@}")" || exit 1
+	json="$(editjson "${json}")" || exit 1
+	request "${options[@]}" --data-binary @- -K <(curlrc url "$(endpoint "${webhook}")") <<< "${json}" > /dev/null || return 1
+}
+
+## Templates call this instead of discord.sh so every send goes through the same checks.
+discordsh() {
+	local i j n footer budget=0 webhook="" values=() files=() secrets=() blocked=() problems=() drop=() message=() split=() part=()
+	local -a args=("${@}") EMBEDS=("${EMBEDS[@]}")
+	local -A seen=()
+
+	for i in "${ATTACHMENTS[@]}" ; do
+		args+=("--file" "${i}")
+	done
+
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		## pending messages were expanded by the requester, the approver's values stay out of them
+		if [[ "${args[i]}" = *"{{"*"}}"* ]] && [[ "${APPROVING}" != "yes" ]] ; then
+			args[i]="$(expand "${args[i]}")" || exit 1
+		fi
+		case "${args[i]}" in
+			("--webhook-url")
+				(( ++i ))
+				webhook="${args[i]}"
+				args[i]="$(threaded "${webhook}")"
+			;;
+			("--webhook-url="*)
+				webhook="${args[i]#*=}"
+				args[i]="--webhook-url=$(threaded "${webhook}")"
+			;;
+			("--color")
+				(( ++i ))
+				args[i]="$(hexcolor "${args[i]}")" || exit 1
+			;;
+			("--field")
+				(( ++i ))
+				args[i]="$(inline "${args[i]}")" || exit 1
+			;;
+			("--file")
+				(( ++i ))
+				if [[ ! -f "${args[i]}" ]] && [[ "${LINT}" != "yes" ]] ; then
+					echo "can't find file \"${args[i]}\" to attach."
+					exit 1
+				fi
+				if [[ -n "${seen["_${args[i]}"]}" ]] ; then
+					drop+=("$(( i - 1 ))" "${i}")
+					continue
+				fi
+				seen["_${args[i]}"]="yes"
+				files+=("${args[i]}")
+			;;
+			(--*=*)
+				values+=("${args[i]#*=}")
+			;;
+			(--*)
+				[[ "${args[i]}" =~ ^--[a-z-]+$ ]] || values+=("${args[i]}")
+			;;
+			(*)
+				values+=("${args[i]}")
+			;;
+		esac
+	done
+	for i in "${drop[@]}" ; do
+		unset "args[i]"
+	done
+	set -- "${args[@]}"
+	args=("${@}")
+
+	## lints print the template line each problem was called from, extra embeds count towards the total
+	if [[ "${LINT}" = "yes" ]] ; then
+		n="$(( $(embedsize "${@}") + ${EMBEDSIZE:-0} ))"
+		{
+			limits "${@}"
+			if (( n > 6000 )) ; then
+				echo -e "embed\tthe embeds have ${n} characters in total, Discord allows 6000"
+			fi
+		} | sed "s/^/${BASH_LINENO[0]}\t/"
+		return 0
+	fi
+
+	if [[ -z "${webhook}" ]] ; then
+		echo "can't find webhook \"${webhook:-NULL}\"."
+		exit 1
+	fi
+	provider "${webhook}" > /dev/null || exit 1
+
+	for (( i = 0 ; i < ${#args[@]} - 1 ; i++ )) ; do
+		if [[ "${args[i]}" = "--description" ]] ; then
+			break
+		fi
+	done
+	## Each part has to fit next to the rest of the embed and the " • part n/N" footer.
+	if [[ "${SPLIT}" = "yes" ]] && [[ "${args[i]}" = "--description" ]] ; then
+		budget="$(( 6000 - $(embedsize "${args[@]:0:i}" "${args[@]:i + 2}") - 16 ))"
+		if (( budget > 4096 )) ; then
+			budget=4096
+		fi
+	fi
+	if [[ "${SPLIT}" = "yes" ]] && [[ "${args[i]}" = "--description" ]] && (( budget > 0 && "${#args[i + 1]}" > budget )) ; then
+		mapfile -t split < <(parts "${args[i + 1]}" "${budget}")
+		local SPLIT="no" ATTACHMENTS=()
+		for (( n = 0 ; n < ${#split[@]} ; n++ )) ; do
+			part=() footer="no"
+			for (( j = 0 ; j < ${#args[@]} ; j++ )) ; do
+				case "${args[j]}" in
+					("--webhook-url")
+						(( ++j ))
+						part+=("--webhook-url" "${webhook}")
+					;;
+					("--webhook-url="*)
+						part+=("--webhook-url=${webhook}")
+					;;
+					("--description")
+						(( ++j ))
+						part+=("--description" "${split[n]}")
+					;;
+					("--footer")
+						(( ++j ))
+						part+=("--footer" "${args[j]} • part $(( n + 1 ))/${#split[@]}")
+						footer="yes"
+					;;
+					("--text"|"--file")
+						(( ++j ))
+						if (( n == 0 )) ; then
+							part+=("${args[j - 1]}" "${args[j]}")
+						fi
+					;;
+					(*)
+						part+=("${args[j]}")
+					;;
+				esac
+			done
+			if [[ "${footer}" = "no" ]] ; then
+				part+=("--footer" "part $(( n + 1 ))/${#split[@]}")
+			fi
+			discordsh "${part[@]}" || exit 1
+			EMBEDS=()
+		done
+		return 0
+	fi
+
+	if [[ "$(provider "${webhook}")" = "discord" ]] ; then
+		part=()
+		for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+			if [[ "${args[i]}" = "--file" ]] ; then
+				(( ++i ))
+			else
+				part+=("${args[i]}")
+			fi
+		done
+		mapfile -t problems < <(payload "${webhook}" "${part[@]}" | payloadlimits)
+	else
+		mapfile -t problems < <(limits "${@}")
+	fi
+	if (( "${#problems[@]}" > 0 )) ; then
+		echo "Discord would refuse this message:"
+		for i in "${problems[@]}" ; do
+			echo -e "\t- ${i#*$'\t'}"
+		done
+		exit 1
+	fi
+
+	mapfile -t secrets < <(printf '%s\n' "${values[@]}" | findsecrets ; (( "${#files[@]}" > 0 )) && findsecrets "${files[@]}")
+	mapfile -t blocked < <(printf '%s\n' "${values[@]}" | findblocked)
+
+	if (( "${#secrets[@]}" > 0 )) ; then
+		echo "this message looks like it contains credentials:"
+		for i in "${secrets[@]}" ; do
+			echo -e "\t- ${i:0:8}..."
+		done
+		confirm "send it anyway?" || exit 1
+	fi
+
+	if (( "${#blocked[@]}" > 0 )) ; then
+		echo "this message contains blocked content:"
+		for i in "${blocked[@]}" ; do
+			echo -e "\t- ${i}"
+		done
+		if [[ "${PROTECTED}" = "yes" ]] ; then
+			echo "refusing to send from a protected profile, edit the content first."
+			exit 1
+		fi
+		confirm "send it anyway?" || exit 1
+	fi
+
+	if (( "${#files[@]}" > 10 )) ; then
+		echo "can't attach ${#files[@]} files, Discord takes 10 in a message."
+		exit 1
+	fi
+	n=0
+	for i in "${files[@]}" ; do
+		if (( "$(stat -c %s "${i}")" > ATTACHMENTLIMIT )) ; then
+			echo "can't attach \"${i}\", it's $(stat -c %s "${i}") bytes and Discord takes ${ATTACHMENTLIMIT}."
+			exit 1
+		fi
+		n="$(( n + $(stat -c %s "${i}") ))"
+	done
+	if (( n > ATTACHMENTSLIMIT )) ; then
+		echo "can't attach ${n} bytes of files, Discord takes ${ATTACHMENTSLIMIT} in a message."
+		exit 1
+	fi
+
+	if [[ "$(provider "${webhook}")" != "discord" ]] && (( "${#files[@]}" > 0 )) ; then
+		echo "$(provider "${webhook}") webhooks can't take attachments, leaving out: ${files[*]##*/}"
+		files=()
+	fi
+
+	## Attachments a provider can't take are left out of the message.
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		if [[ "${args[i]}" = "--file" ]] && (( "${#files[@]}" == 0 )) ; then
+			(( ++i ))
+			continue
+		fi
+		message+=("${args[i]}")
+	done
+
+	if [[ "${DRYRUN}" = "json" ]] ; then
+		if ! command -v jq &> /dev/null ; then
+			echo "requirement jq not found."
+			exit 1
+		fi
+		i="$(payload "${webhook}" "${message[@]}")" || exit 1
+		jq . <<< "${i}" 2> /dev/null || echo "${i}"
+		for i in "${files[@]}" ; do
+			echo "attachment: ${i##*/} ($(numfmt --to=iec-i --suffix=B "$(stat -c %s "${i}")"))"
+		done
+		return 0
+	elif [[ "${DRYRUN}" = "yes" ]] ; then
+		case "$(provider "${webhook}")" in
+			("email")
+				echo "SMTP ${SMTPURL} from ${MAILFROM} to ${webhook#email:}"
+			;;
+			("generic")
+				echo "${METHOD:-"POST"} $(endpoint "${webhook}")"
+			;;
+			(*)
+				echo "POST $(endpoint "${webhook}")"
+			;;
+		esac
+		i="$(payload "${webhook}" "${message[@]}")" || exit 1
+		jq . <<< "${i}" 2> /dev/null || echo "${i}"
+		for i in "${files[@]}" ; do
+			echo "attachment: ${i##*/} ($(numfmt --to=iec-i --suffix=B "$(stat -c %s "${i}")"))"
+		done
+		return 0
+	fi
+
+	if [[ -n "${BUNDLE}" ]] ; then
+		bundle "${webhook}" "${@}" > "${BUNDLE}"
+		echo "bundle written to \"${BUNDLE}\"."
+		return 0
+	fi
+
+	if [[ "${APPROVAL}" = "yes" ]] && [[ "${APPROVING}" != "yes" ]] ; then
+		pending "${@}"
+		return 0
+	fi
+
+	throttle "${webhook}"
+	if ! deliver "${webhook}" "${message[@]}" ; then
+		echo "sending to $(provider "${webhook}") failed."
+		exit 1
+	fi
+	sent "${webhook}"
+	remember "${@}"
+	archive "${webhook}" "${@}"
+}
+
+## Prints its arguments as a JSON array of strings, each one prefixed so jq doesn't take it for an option.
+jsonarray() {
+	jq -n '$ARGS.positional | map(.[1:])' --args "${@/#/:}"
+}
+
+## Parks a message in PENDINGDIR for --approve as JSON: the arguments with copies of their attachments,
+## and the provider settings the template resolved, so the approver sends it as it was rendered.
+pending() {
+	local id i name json settings=() args=("${@}")
+
+	id="$(date +%Y%m%d%H%M%S)-${RANDOM}"
+	mkdir -p "${PENDINGDIR}"
+	(
+		## a setgid PENDINGDIR is shared with its group, so the approvers can read and remove it
+		if [[ -g "${PENDINGDIR}" ]] ; then
+			umask 007
+		else
+			umask 077
+		fi
+		for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+			if [[ "${args[i]}" = "--file" ]] ; then
+				(( ++i ))
+				mkdir -p "${PENDINGDIR}/${id}.files"
+				cp "${args[i]}" "${PENDINGDIR}/${id}.files/"
+				args[i]="${args[i]##*/}"
+			fi
+		done
+		for name in "${PENDINGSETTINGS[@]}" ; do
+			settings+=("${name}" "${!name}")
+		done
+		json="$(jq -n --arg template "${TEMPLATE}" \
+			--argjson settings "$(jsonarray "${settings[@]}" | jq '[range(0; length; 2) as $i | {(.[$i]): .[$i + 1]}] | add // {}')" \
+			--argjson headers "$(jsonarray "${HEADERS[@]}")" \
+			--argjson embeds "$(printf '%s\n' "${EMBEDS[@]}" | jq -s .)" \
+			--argjson args "$(jsonarray "${args[@]}")" \
+			'{template: $template, settings: $settings, headers: $headers, embeds: $embeds, args: $args}')" || exit 1
+		echo "${json}" > "${PENDINGDIR}/${id}"
+	) || {
+		rm -rf "${PENDINGDIR:?}/${id}.files"
+		exit 1
+	}
+	echo "waiting for approval, run: ${0} --approve ${id}"
+}
+
+## Sends a message pending() parked. Only the settings in PENDINGSETTINGS are read back, as data,
+## attachments only come from the message's own directory and the arguments aren't expanded again.
+approve() {
+	local file="${1}" name value i
+	local -a args=()
+
+	TEMPLATE="$(jq -r '.template // ""' "${file}")"
+	while IFS= read -r -d '' name && IFS= read -r -d '' value ; do
+		if [[ " ${PENDINGSETTINGS[*]} " = *" ${name} "* ]] ; then
+			printf -v "${name}" '%s' "${value}"
+		fi
+	done < <(jq -j '.settings // {} | to_entries[] | "\(.key)\u0000\(.value)\u0000"' "${file}")
+	mapfile -d '' -t HEADERS < <(jq -j '.headers // [] | .[] | "\(.)\u0000"' "${file}")
+	mapfile -d '' -t EMBEDS < <(jq -j '.embeds // [] | .[] | "\(tojson)\u0000"' "${file}")
+	mapfile -d '' -t args < <(jq -j '.args // [] | .[] | "\(.)\u0000"' "${file}")
+
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		if [[ "${args[i]}" = "--file" ]] ; then
+			(( ++i ))
+			args[i]="${file}.files/${args[i]##*/}"
+		fi
+	done
+
+	THREAD="" SPLIT="no" ATTACHMENTS=() APPROVING="yes"
+	discordsh "${args[@]}"
+}
+
+## Prints the template, the arguments it was rendered with and the payload the webhook would get
+## as one JSON document, leaving the webhook out so the bundle can be shared.
+bundle() {
+	if ! command -v jq &> /dev/null ; then
+		echo "requirement jq not found." >&2
+		exit 1
+	fi
+
+	local webhook="${1}" source="${TEMPLATE}" json arg skip="no" keep=()
+	shift
+
+	if [[ ! -f "${source}" ]] ; then
+		source="${TEMPLATE}.sh"
+	fi
+
+	for arg in "${@}" ; do
+		if [[ "${skip}" = "yes" ]] ; then
+			skip="no"
+		elif [[ "${arg}" = "--webhook-url" ]] ; then
+			skip="yes"
+		elif [[ "${arg}" != "--webhook-url="* ]] ; then
+			keep+=("${arg}")
+		fi
+	done
+
+	json="$(payload "${webhook}" "${@}")" || exit 1
+	jq --arg template "${TEMPLATE#"${TEMPLATEDIR}/"}" --rawfile source "${source}" --argjson args "$(jsonarray "${keep[@]}")" '{
+		template: $template,
+		source: $source,
+		args: $args,
+		payload: .
+	}' <<< "${json}"
+}
+
+## Renders a Discord webhook payload (JSON) as plain text, read from the file or stdin.
+preview() {
+	if ! command -v jq &> /dev/null ; then
+		echo "requirement jq not found."
+		exit 1
+	fi
+
+	local bar="┃ " separator=" • "
+
+	if [[ "${ACCESSIBLE}" = "yes" ]] ; then
+		bar="" separator=", "
+	fi
+
+	jq -r --arg bar "${bar}" --arg separator "${separator}" '
+		(.username // "webhook"), (.content // empty),
+		(.embeds // [] | .[] | "", ([
+			(.author.name // empty),
+			(.title // empty | "**\(.)**"),
+			(.description // empty),
+			(.fields // [] | .[] | "\(.name)\(if .inline then " (inline)" else "" end): \(.value)"),
+			(.image.url // empty | "[image] \(.)"),
+			(.thumbnail.url // empty | "[thumbnail] \(.)"),
+			([.footer.text // empty, .timestamp // empty] | select(length > 0) | join($separator))
+		] | join("\n") | split("\n") | map($bar + .) | .[]))
+	' "${@}"
+}
+
+## Turns a Discord message/webhook payload (JSON), or the payload of an exported bundle, into "VARIABLE<TAB>option<TAB>value" lines, one per
+## thing the first embed uses (VARIABLE is "-" for plain flags), newlines written the way discord.sh takes them ("\n").
+importable() {
+	if ! command -v jq &> /dev/null ; then
+		echo "requirement jq not found."
+		exit 1
+	fi
+
+	jq -r '
+		(.payload // .) | (if .embeds then .embeds[0] else . end) as $embed | [
+			["TEXT", "--text", .content],
+			["SERVERNAME", "--username", .username],
+			["AVATAR", "--avatar", .avatar_url],
+			["AUTHOR", "--author", $embed.author.name],
+			["AUTHORURL", "--author-url", $embed.author.url],
+			["AUTHORICON", "--author-icon", $embed.author.icon_url],
+			["TITLE", "--title", $embed.title],
+			["URL", "--url", $embed.url],
+			["DESCRIPTION", "--description", $embed.description],
+			["COLOR", "--color", $embed.color],
+			($embed.fields // [] | to_entries[] | ["FIELD\(.key + 1)", "--field", "\(.value.name);\(.value.value);\(.value.inline // false)"]),
+			["BANNER", "--image", $embed.image.url],
+			["THUMBNAIL", "--thumbnail", $embed.thumbnail.url],
+			["FOOTER", "--footer", $embed.footer.text],
+			["FOOTERICON", "--footer-icon", $embed.footer.icon_url],
+			["-", "--timestamp", $embed.timestamp]
+		] | .[] | select(.[2] != null and .[2] != "") | map(tostring | gsub("\n"; "\\n")) | join("\t")
+	' "${1}"
+}
+
+## Finds a template as given, in TEMPLATEDIR or in the synced SHAREDDIR, local ones win.
+findtemplate() {
+	local dir
+
+	for dir in "" "${TEMPLATEDIR}/" "${SHAREDDIR}/" ; do
+		if [[ -f "${dir}${1}" ]] || [[ -f "${dir}${1}.sh" ]] ; then
+			echo "${dir}${1}"
+			return 0
+		fi
+	done
+
+	echo "${1}"
+}
+
+## Turns target names from TARGETS into "name<TAB>target" lines, following groups, targets given
+## as they are (anything with a ":") are named after their beginning.
+resolve() {
+	local name target
+	local -a members
+
+	for name in "${@}" ; do
+		if [[ -n "${name}" ]] && [[ -n "${TARGETS[${name}]}" ]] ; then
+			for target in "${RESOLVING[@]}" ; do
+				if [[ "${target}" = "${name}" ]] ; then
+					echo "target groups include each other in a cycle: ${RESOLVING[*]} ${name}" >&2
+					return 1
+				fi
+			done
+			RESOLVING+=("${name}")
+			read -r -a members <<< "${TARGETS[${name}]}"
+			for target in "${members[@]}" ; do
+				if [[ -n "${TARGETS[${target}]}" ]] ; then
+					resolve "${target}" || return 1
+				elif [[ "${target}" = *:* ]] ; then
+					echo -e "${name}\t${target}"
+				else
+					echo "can't find target \"${target}\" of \"${name}\"." >&2
+					return 1
+				fi
+			done
+			unset "RESOLVING[-1]"
+		elif [[ "${name}" = *:* ]] ; then
+			target="${name}"
+			if (( "${#name}" > 32 )) ; then
+				target="${name:0:32}..."
+			fi
+			echo -e "${target}\t${name}"
+		else
+			echo "can't find target \"${name:-NULL}\"." >&2
+			return 1
+		fi
+	done
+}
+
+## Runs the template for every target at once, then prints each one's output and whether it went out.
+broadcast() {
+	local i lines failed=0
+	local -a names=() targets=() pids=()
+
+	lines="$(resolve "${@}")" || exit 1
+	while IFS=$'\t' read -r i lines ; do
+		names+=("${i}")
+		targets+=("${lines}")
+	done <<< "$(sort -u -t $'\t' -k 2,2 <<< "${lines}")"
+
+	for i in "${!targets[@]}" ; do
+		( TARGET="${targets[i]}" ; push ) < /dev/null > "${WORKDIR}/target.${i}" 2>&1 &
+		pids[i]="${!}"
+	done
+
+	for i in "${!targets[@]}" ; do
+		if wait "${pids[i]}" ; then
+			cat "${WORKDIR}/target.${i}"
+			report ok "${names[i]}"
+		else
+			head -n -1 "${WORKDIR}/target.${i}"
+			report error "${names[i]}" "$(tail -n 1 "${WORKDIR}/target.${i}")"
+			(( ++failed ))
+		fi
+	done
+
+	(( failed == 0 ))
+}
+
+## Loads the template's conf files and variables, then runs the template. A template conf
+## with LOCALES=(en tr) runs it once per locale, each with its own <template>.<locale>.conf,
+## otherwise the conf of --lang (or $LANG, "tr" for tr_TR.UTF-8) is loaded when there is one.
+## With targets in TO (--to, or the template's "## targets:") it runs once per target, see broadcast.
+push() {
+	local i value missing=()
+	local -A locked=()
+
+	TEMPLATE="$(findtemplate "${TEMPLATE}")"
+
+	if hasconf "${TEMPLATE}.conf" ; then
+		loadconf "${TEMPLATE}.conf"
+	elif hasconf "${TEMPLATE}.sh.conf" ; then
+		loadconf "${TEMPLATE}.sh.conf"
+	elif hasconf "${TEMPLATE}.conf.sh" ; then
+		loadconf "${TEMPLATE}.conf.sh"
+	fi
+
+	if [[ -z "${LOCALE}" ]] && (( "${#LOCALES[@]}" > 0 )) ; then
+		for i in "${LOCALES[@]}" ; do
+			( LOCALE="${i}" ; push )
+		done
+		return 0
+	fi
+
+	if [[ -z "${TARGET}" ]] && (( "${#TO[@]}" == 0 )) ; then
+		i="${TEMPLATE}"
+		[[ -f "${i}" ]] || i="${TEMPLATE}.sh"
+		if [[ -f "${i}" ]] ; then
+			read -r -a TO <<< "$(metadata "${i}" targets | head -n 1 | tr "," " ")"
+		fi
+	fi
+	if [[ -z "${TARGET}" ]] && (( "${#TO[@]}" > 0 )) && [[ "${LINT}" != "yes" ]] ; then
+		broadcast "${TO[@]}"
+		return
+	fi
+
+	i="${LOCALE:-"${LANG%%[_.]*}"}"
+	if [[ -n "${i}" ]] && hasconf "${TEMPLATE}.${i}.conf" ; then
+		loadconf "${TEMPLATE}.${i}.conf"
+	fi
+
+	for i in "${LOCKED[@]}" ; do
+		locked["${i}"]="${!i}"
+	done
+
+	if hasconf "${GLOBALS}" ; then
+		loadconf "${GLOBALS}"
+	fi
+
+	for i in "${VARSFILES[@]}" ; do
+		if hasconf "${i}" ; then
+			loadvars "${i}"
+		else
+			echo "can't find vars file \"${i}\"."
+			exit 1
+		fi
+	done
+
+	for i in "${!OVERRIDES[@]}" ; do
+		printf -v "${i}" '%s' "${OVERRIDES[${i}]}"
+	done
+
+	for i in "${!locked[@]}" ; do
+		if [[ "${!i}" != "${locked[${i}]}" ]] ; then
+			echo "${i} is locked by the profile, ignoring the new value."
+			printf -v "${i}" '%s' "${locked[${i}]}"
+		fi
+	done
+
+	## Interaction follow-ups share the webhook endpoint: /webhooks/<application id>/<interaction token>.
+	if [[ -n "${INTERACTION}" ]] ; then
+		WEBHOOK="https://discord.com/api/webhooks/${INTERACTION}"
+	fi
+
+	if [[ -n "${TARGET}" ]] ; then
+		WEBHOOK="${TARGET}" PROVIDER=""
+	fi
+
+	## Mail can't go out without a sender and a server.
+	if [[ "$(provider "${WEBHOOK}" 2> /dev/null)" = "email" ]] ; then
+		REQUIRED+=("MAILFROM" "SMTPURL")
+	fi
+
+	for i in "${REQUIRED[@]}" ; do
+		[[ -n "${!i}" ]] || missing+=("${i}")
+	done
+	if (( "${#missing[@]}" > 0 )) && [[ "${LINT}" != "yes" ]] ; then
+		echo "required values are missing: ${missing[*]}"
+		exit 1
+	fi
+
+	for i in "${MAXLENGTH[@]}" ; do
+		value="${i%%:*}"
+		value="${!value}"
+		value="${value//\\n/$'\n'}"
+		if (( "${#value}" > "${i##*:}" )) ; then
+			echo "${i%%:*} is too long: ${#value}/${i##*:} characters"
+			exit 1
+		fi
+	done
+
+	if [[ -f "${TEMPLATE}" ]] ; then
+		source "${TEMPLATE}"
+	elif [[ -f "${TEMPLATE}.sh" ]] ; then
+		source "${TEMPLATE}.sh"
+	else
+		echo "can't find template \"${TEMPLATE:-NULL}\"."
+		exit 1
+	fi
+}
+
+## Adds another embed to the template's next message, with discord.sh's embed options:
+## embed --title "Changes" --description "..." --color "#2e954d"
+## Discord shows up to 10 embeds in a message, other providers only the message's own one.
+embed() {
+	local i json
+	local -a args=("${@}")
+
+	if (( "${#EMBEDS[@]}" >= 9 )) ; then
+		echo "a message can't have more than 10 embeds."
+		exit 1
+	fi
+
+	for (( i = 0 ; i < ${#args[@]} ; i++ )) ; do
+		if [[ "${args[i]}" = *"{{"*"}}"* ]] ; then
+			args[i]="$(expand "${args[i]}")" || exit 1
+		fi
+		if [[ "${args[i]}" = "--color" ]] ; then
+			(( ++i ))
+			args[i]="$(hexcolor "${args[i]}")" || exit 1
+		fi
+	done
+
+	if [[ "${LINT}" = "yes" ]] ; then
+		limits "${args[@]}" | sed "s/^/${BASH_LINENO[0]}\t/"
+		EMBEDSIZE="$(( ${EMBEDSIZE:-0} + $(embedsize "${args[@]}") ))"
+		return 0
+	fi
+
+	json="$("${DISCORDSH}" --dry-run --webhook-url "${WEBHOOK}" "${args[@]}")" || exit 1
+	json="$(jq -c '.embeds[0] // empty' <<< "${json}")" || exit 1
+	if [[ -n "${json}" ]] ; then
+		EMBEDS+=("${json}")
+	fi
+}
+
+## Reads a --shell line into REPLY, from the --replay script when there is one: it is typed out
+## after REPLAYDELAY seconds (1 by default) at a fixed pace, so demos come out the same every time.
+prompt() {
+	local i
+
+	if [[ -z "${REPLAY}" ]] ; then
+		read -r -e -p "${1}"
+		return
+	fi
+
+	IFS= read -r REPLY <&3 || return 1
+	printf '%s' "${1}"
+	sleep "${REPLAYDELAY:-1}"
+	for (( i = 0 ; i < ${#REPLY} ; i++ )) ; do
+		printf '%s' "${REPLY:i:1}"
+		sleep 0.05
+	done
+	echo
+}
+
+## Downloads a remote file so it can be attached for real, keeping its name:
+## --file "$(download "${URL}")"
+download() {
+	local name="${1%%\?*}"
+	local file="${WORKDIR}/download/${RANDOM}/${name##*/}"
+
+	if [[ "${LINT}" = "yes" ]] ; then
+		echo "${1}"
+		return 0
+	fi
+
+	mkdir -p "${file%/*}"
+	if ! request -L --max-filesize "${ATTACHMENTLIMIT}" -o "${file}" "${1}" ; then
+		echo "can't download \"${1}\" (larger than ${ATTACHMENTLIMIT} bytes or unreachable)." >&2
+		return 1
+	elif (( "$(stat -c %s "${file}")" > ATTACHMENTLIMIT )) ; then
+		echo "\"${1}\" is larger than ${ATTACHMENTLIMIT} bytes." >&2
+		return 1
+	fi
+
+	echo "${file}"
+}
+
+## Limits a variable to a list of options, for templates: choose SEVERITY low medium critical
+## An empty value is picked from a menu when there is a terminal.
+choose() {
+	local name="${1}" option
+	shift
+
+	if [[ -z "${!name}" ]] && [[ -t 0 ]] ; then
+		PS3="${name}: "
+		select option in "${@}" ; do
+			[[ -n "${option}" ]] && break
+		done
+		printf -v "${name}" '%s' "${option}"
+	fi
+
+	for option in "${@}" ; do
+		[[ "${!name}" = "${option}" ]] && return 0
+	done
+
+	echo "${name} must be one of: ${*} (got \"${!name}\")."
+	exit 1
+}
+
+## Makes sure a variable is a number within optional bounds, for templates: number REPLICAS 1 10
+number() {
+	local name="${1}" min="${2}" max="${3}"
+
+	if [[ ! "${!name}" =~ ^-?[0-9]+([.][0-9]+)?$ ]] ; then
+		echo "${name} must be a number (got \"${!name}\")."
+		exit 1
+	elif [[ -n "${min}" ]] && awk "BEGIN { exit !(${!name} < ${min}) }" ; then
+		echo "${name} must be at least ${min} (got ${!name})."
+		exit 1
+	elif [[ -n "${max}" ]] && awk "BEGIN { exit !(${!name} > ${max}) }" ; then
+		echo "${name} must be at most ${max} (got ${!name})."
+		exit 1
+	fi
+}
+
+## Turns a date anything "date -d" understands into a Discord timestamp (style t, T, d, D, f, F or R)
+## or ISO-8601 (style iso), for templates: --description "starts $(datetime "${START}" R)"
+## An empty date is asked for when there is a terminal.
+datetime() {
+	local value="${1}" style="${2:-"f"}" unix
+
+	if [[ -z "${value}" ]] && [[ -t 0 ]] ; then
+		read -r -p "date/time (e.g. \"tomorrow 15:00\"): " value
+	fi
+
+	if ! unix="$(date -d "${value:-now}" +%s 2> /dev/null)" ; then
+		echo "can't understand the date \"${value}\"." >&2
+		return 1
+	fi
+
+	case "${style}" in
+		("iso")
+			date -d "@${unix}" +%FT%T%:z
+		;;
+		("t"|"T"|"d"|"D"|"f"|"F"|"R")
+			echo "<t:${unix}:${style}>"
+		;;
+		(*)
+			echo "unknown timestamp style \"${style}\"." >&2
+			return 1
+		;;
+	esac
+}
+
+## Shows roughly how Discord renders a timestamp of the given style for the unix time.
+humantime() {
+	local diff count unit
+
+	case "${2}" in
+		("t") date -d "@${1}" "+%H:%M" ;;
+		("T") date -d "@${1}" "+%H:%M:%S" ;;
+		("d") date -d "@${1}" "+%d/%m/%Y" ;;
+		("D") date -d "@${1}" "+%B %-d, %Y" ;;
+		("f") date -d "@${1}" "+%B %-d, %Y %H:%M" ;;
+		("F") date -d "@${1}" "+%A, %B %-d, %Y %H:%M" ;;
+		("R")
+			diff="$(( ${1} - $(date +%s) ))"
+			for unit in "86400 days" "3600 hours" "60 minutes" "1 seconds" ; do
+				if (( ${diff#-} >= ${unit% *} )) || [[ "${unit% *}" = "1" ]] ; then
+					break
+				fi
+			done
+			count="$(( ${diff#-} / ${unit% *} ))"
+			unit="${unit#* }"
+			if (( count == 1 )) ; then
+				unit="${unit%s}"
+			fi
+			if (( diff < 0 )) ; then
+				echo "${count} ${unit} ago"
+			else
+				echo "in ${count} ${unit}"
+			fi
+		;;
+	esac
+}
+
+## Runs a value through a chain of transforms, for templates:
+## --description "$(transform "${DESCRIPTION}" trim truncate:200 codeblock:rust)"
+## Markdown ones: bold, italic, underline, strikethrough, spoiler, code, quote and codeblock[:language].
+transform() {
+	local value="${1}" step zwsp=$'\xe2\x80\x8b'
+	shift
+
+	for step in "${@}" ; do
+		case "${step}" in
+			("trim")
+				value="${value#"${value%%[![:space:]]*}"}"
+				value="${value%"${value##*[![:space:]]}"}"
+			;;
+			("truncate:"*)
+				value="${value:0:${step#*:}}"
+			;;
+			("uppercase")
+				value="${value^^}"
+			;;
+			("lowercase")
+				value="${value,,}"
+			;;
+			("bold")
+				value="**${value}**"
+			;;
+			("italic")
+				value="*${value}*"
+			;;
+			("underline")
+				value="__${value}__"
+			;;
+			("strikethrough")
+				value="~~${value}~~"
+			;;
+			("spoiler")
+				value="||${value}||"
+			;;
+			("code")
+				if [[ "${value}" = *'`'* ]] ; then
+					value='`` '"${value}"' ``'
+				else
+					value='`'"${value}"'`'
+				fi
+			;;
+			("quote")
+				value="> ${value//\\n/\\n> }"
+			;;
+			("codeblock"|"codeblock:"*)
+				[[ "${step}" = *:* ]] || step="codeblock:"
+				## a zero width space keeps ``` inside the code from closing the block
+				value="${value//'```'/"\`${zwsp}\`${zwsp}\`"}"
+				value='```'"${step#*:}"'\n'"${value}"'\n```'
+			;;
+			(*)
+				echo "unknown transform \"${step}\"." >&2
+				return 1
+			;;
+		esac
+	done
+
+	printf '%s' "${value}"
+}
+
+## Templates describe themselves with "## key: value" comment lines, prints every value of the key.
+metadata() {
+	sed -n "s/^## ${2}:[[:space:]]*//p" "${1}"
+}
+
+## Prints the template's "## icon:" followed by a space, or a default one, nothing in accessible mode.
+icon() {
+	local icon
+
+	[[ "${ACCESSIBLE}" = "yes" ]] && return 0
+	icon="$(metadata "${1}" icon | head -n 1)"
+	echo "${icon:-"📄"} "
+}
+
+## Lists every template file under TEMPLATEDIR and SHAREDDIR.
+templates() {
+	local dirs=("${TEMPLATEDIR}")
+
+	[[ -d "${SHAREDDIR}" ]] && dirs+=("${SHAREDDIR}")
+	find "${dirs[@]}" -mindepth 1 \( -name ".*" -o -path "${TEMPLATEDIR}/bin" \) -prune -o -type f -name "*.sh" \
+		! -name "*.conf.sh" ! -name "conf.sh" ! -name "templates.sh" -print | sort
+}
+
+## Matches the letters of the pattern in order anywhere in the text (case-insensitive) and prints
+## the text with the matched letters highlighted when HIGHLIGHT="yes".
+fuzzy() {
+	local pattern="${1,,}" text="${2}" out="" char i j=0 on="" off=""
+
+	if [[ "${HIGHLIGHT}" = "yes" ]] ; then
+		on="\e[1;33m" off="\e[0m"
+	fi
+
+	for (( i = 0 ; i < ${#text} ; i++ )) ; do
+		char="${text:i:1}"
+		if (( j < ${#pattern} )) && [[ "${char,,}" = "${pattern:j:1}" ]] ; then
+			out+="${on}${char}${off}"
+			j="$(( j + 1 ))"
+		else
+			out+="${char}"
+		fi
+	done
+
+	(( j == ${#pattern} )) || return 1
+	echo "${out}"
+}
+
+## Prints "name<TAB>file" for every template, names relative to their directory and sorted.
+catalog() {
+	local file name
+
+	while read -r file ; do
+		name="${file#"${TEMPLATEDIR}/"}"
+		name="${name#"${SHAREDDIR}/"}"
+		echo -e "${name%.sh}\t${file}"
+	done < <(templates) | sort -s -t $'\t' -k 1,1 -u
+}
+
+## Returns 0 when the template is marked "## pinned: yes".
+pinned() {
+	[[ "$(metadata "${1}" pinned | head -n 1)" =~ ^([Yy]es|[Tt]rue|1)$ ]]
+}
+
+## Prints the catalog grouped by category, pinned templates first, then by "## priority:" (higher
+## first, 0 when unset) and name.
+ranked() {
+	local name file dir pin priority
+
+	while IFS=$'\t' read -r name file ; do
+		dir="."
+		if [[ "${name}" = */* ]] ; then
+			dir="${name%/*}"
+		fi
+		pin=1
+		if pinned "${file}" ; then
+			pin=0
+		fi
+		priority="$(metadata "${file}" priority | head -n 1)"
+		if [[ ! "${priority}" =~ ^-?[0-9]+$ ]] ; then
+			priority=0
+		fi
+		echo -e "${dir}\t${pin}\t${priority}\t${name}\t${file}"
+	done < <(catalog) | sort -s -t $'\t' -k 1,1 -k 2,2n -k 3,3nr -k 4,4 | cut -f 4,5
+}
 
-if [[ -f "${CONFIG}"  ]] ; then
-	source "${CONFIG}"
-fi
+## Prints one line of a lint report, coloured when stdout is a terminal.
+report() {
+	local color=""
 
-if [[ -f "bin/discord/discord.sh" ]] ; then
-	alias discordsh='bin/discord/discord.sh'
-else
-	echo -e "requirement discord.sh not found. You can try:\n\t- git submodule init\n\t- git submodule update\nor:\n\t- git clone https://github.com/fieu/discord.sh.git \"bin/discord\""
-	exit 1
-fi
+	if [[ -t 1 ]] && [[ "${ACCESSIBLE}" != "yes" ]] ; then
+		case "${1}" in
+			("ok") color="\e[32m" ;;
+			("warning") color="\e[33m" ;;
+			("error") color="\e[31m" ;;
+		esac
+	fi
+
+	echo -e "${color}${1}${color:+"\e[0m"}\t${2#"${TEMPLATEDIR}/"}${3:+": ${3}"}"
+}
+
+## Checks templates for shell syntax errors, missing sends and Discord's limits (rendered with
+## their default values), fails if any template is broken.
+validate() {
+	local file output line option problem errors=0
+
+	for file in "${@}" ; do
+		if [[ ! -f "${file}" ]] ; then
+			report error "${file}" "no such template"
+			(( ++errors ))
+		elif ! output="$(bash -n "${file}" 2>&1)" ; then
+			report error "${file}" "${output//$'\n'/" "}"
+			(( ++errors ))
+		elif ! grep -q "discordsh" "${file}" ; then
+			report warning "${file}" "never calls discordsh"
+		elif ! output="$( ( LINT="yes" TEMPLATE="${file}" ; push ) < /dev/null 2>&1 )" ; then
+			report warning "${file}" "can't render it with default values: ${output##*$'\n'}"
+		elif grep -qE $'^[0-9]+\t(--[a-z-]+|embed)\t' <<< "${output}" ; then
+			while IFS=$'\t' read -r line option problem ; do
+				report error "${file}" "line ${line}: ${problem}"
+			done < <(grep -E $'^[0-9]+\t(--[a-z-]+|embed)\t' <<< "${output}")
+			(( ++errors ))
+		else
+			report ok "${file}"
+		fi
+	done
+
+	(( errors == 0 ))
+}
 
 ## Only long options allowed here.
 while (( "${#}" > 0 )) ; do
@@ -23,6 +2066,291 @@ while (( "${#}" > 0 )) ; do
 			shift
 			export SETOPT="push"
 		;;
+		("--yes")
+			shift
+			export ASSUMEYES="yes"
+		;;
+		("--accessible")
+			shift
+			export ACCESSIBLE="yes"
+		;;
+		("--dry-run")
+			shift
+			export DRYRUN="yes"
+		;;
+		("--json")
+			shift
+			export DRYRUN="json"
+		;;
+		("--snippets")
+			shift
+			export SETOPT="snippets"
+		;;
+		("--set")
+			shift
+			export SETOPT="set"
+			[[ -n "${1}" ]] && {
+				export SETVAR="${1}"
+				shift
+			}
+		;;
+		("--globals")
+			shift
+			export SETOPT="globals"
+		;;
+		("--history")
+			shift
+			export SETOPT="history"
+			while (( "${#}" > 0 )) && [[ "${1}" != --* ]] ; do
+				SEARCH+=("${1}")
+				shift
+			done
+		;;
+		("--delete")
+			shift
+			export SETOPT="delete"
+			[[ -n "${1}" ]] && {
+				export MESSAGEID="${1}"
+				shift
+			}
+		;;
+		("--webhook-info")
+			shift
+			export SETOPT="webhook-info"
+		;;
+		("--datetime")
+			shift
+			export SETOPT="datetime"
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export DATETIME="${1}"
+				shift
+			}
+		;;
+		("--since"|"--until")
+			if [[ -z "${2}" ]] || ! date -d "${2}" &> /dev/null ; then
+				echo "can't understand the date \"${2}\"."
+				exit 1
+			fi
+			if [[ "${1}" = "--since" ]] ; then
+				export SINCE="$(date -d "${2}" +%F)"
+			else
+				export UNTIL="$(date -d "${2}" +%F)"
+			fi
+			shift 2
+		;;
+		("--resend")
+			shift
+			export SETOPT="resend"
+			[[ -n "${1}" ]] && {
+				export RESEND="${1}"
+				shift
+			}
+		;;
+		("--export-state"|"--import-state")
+			export SETOPT="${1#--}"
+			shift
+			[[ -n "${1}" ]] && {
+				export STATEFILE="${1}"
+				shift
+			}
+		;;
+		("--no-secrets")
+			shift
+			export SECRETS="no"
+		;;
+		("--interaction")
+			shift
+			[[ -n "${1}" ]] && {
+				export INTERACTION="${1}"
+				shift
+			}
+		;;
+		("--split")
+			shift
+			export SPLIT="yes"
+		;;
+		("--edit-json")
+			shift
+			export EDITJSON="yes"
+		;;
+		("--to")
+			shift
+			[[ -n "${1}" ]] && {
+				TO+=("${1}")
+				shift
+			}
+		;;
+		("--thread")
+			shift
+			[[ -n "${1}" ]] && {
+				export THREAD="${1}"
+				shift
+			}
+		;;
+		("--thread-name")
+			shift
+			[[ -n "${1}" ]] && {
+				export THREADNAME="${1}"
+				shift
+			}
+		;;
+		("--flags")
+			shift
+			[[ -n "${1}" ]] && {
+				export FLAGS="${1}"
+				shift
+			}
+		;;
+		("--attach")
+			shift
+			[[ -n "${1}" ]] && {
+				ATTACHMENTS+=("${1}")
+				shift
+			}
+		;;
+		("--vars")
+			shift
+			[[ -n "${1}" ]] && {
+				VARSFILES+=("${1}")
+				shift
+			}
+		;;
+		("--view")
+			shift
+			export SETOPT="view"
+			[[ -n "${1}" ]] && {
+				export PAYLOAD="${1}"
+				shift
+			}
+		;;
+		("--validate")
+			shift
+			export SETOPT="validate"
+			while (( "${#}" > 0 )) && [[ "${1}" != --* ]] ; do
+				VALIDATE+=("${1}")
+				shift
+			done
+		;;
+		("--watch")
+			shift
+			export WATCH="yes"
+		;;
+		("--pending")
+			shift
+			export SETOPT="pending"
+		;;
+		("--approve"|"--reject")
+			export SETOPT="${1#--}"
+			shift
+			[[ -n "${1}" ]] && {
+				export PENDINGID="${1}"
+				shift
+			}
+		;;
+		("--list")
+			shift
+			export SETOPT="list"
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export CATEGORY="${1%/}"
+				shift
+			}
+		;;
+		("--import")
+			shift
+			export SETOPT="import"
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export PAYLOAD="${1}"
+				shift
+			}
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export NEWTEMPLATE="${1}"
+				shift
+			}
+		;;
+		("--export")
+			shift
+			export SETOPT="export"
+			[[ -n "${1}" ]] && {
+				export BUNDLE="${1}"
+				shift
+			}
+		;;
+		("--duplicate")
+			shift
+			export SETOPT="duplicate"
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export TEMPLATE="${1}"
+				shift
+			}
+			[[ -n "${1}" ]] && [[ "${1}" != --* ]] && {
+				export NEWTEMPLATE="${1}"
+				shift
+			}
+		;;
+		("--edit")
+			shift
+			export SETOPT="edit"
+			[[ -n "${1}" ]] && {
+				export TEMPLATE="${1}"
+				shift
+			}
+		;;
+		("--filter")
+			shift
+			[[ -n "${1}" ]] && {
+				export FILTER="${1}"
+				shift
+			}
+		;;
+		("--tag")
+			shift
+			[[ -n "${1}" ]] && {
+				export TAG="${1}"
+				shift
+			}
+		;;
+		("--tags")
+			shift
+			export SETOPT="tags"
+		;;
+		("--info")
+			shift
+			export SETOPT="info"
+			[[ -n "${1}" ]] && {
+				export TEMPLATE="${1}"
+				shift
+			}
+		;;
+		("--sync")
+			shift
+			export SETOPT="sync"
+		;;
+		("--shell")
+			shift
+			export SETOPT="shell"
+		;;
+		("--replay")
+			shift
+			export SETOPT="shell"
+			[[ -n "${1}" ]] && {
+				export REPLAY="${1}"
+				shift
+			}
+		;;
+		("--lang")
+			shift
+			[[ -n "${1}" ]] && {
+				export LOCALE="${1}"
+				shift
+			}
+		;;
+		("--templates-dir")
+			shift
+			[[ -n "${1}" ]] && {
+				export TEMPLATEDIR="${1}"
+				shift
+			}
+		;;
 		("--template")
 			shift
 			[[ -n "${1}" ]] && {
@@ -38,24 +2366,478 @@ done
 
 case "${SETOPT:-help}" in
 	("push")
-		if [[ -f "${TEMPLATE}.conf" ]] ; then
-			source "${TEMPLATE}.conf"
-		elif [[ -f "${TEMPLATE}.sh.conf" ]] ; then
-			source "${TEMPLATE}.sh.conf"
-		elif [[ -f "${TEMPLATE}.conf.sh" ]] ; then
-			source "${TEMPLATE}.conf.sh"
+		push
+	;;
+	("shell")
+		set -o history
+		if [[ -n "${REPLAY}" ]] ; then
+			if [[ ! -f "${REPLAY}" ]] ; then
+				echo "can't find replay script \"${REPLAY}\"."
+				exit 1
+			fi
+			exec 3< "${REPLAY}"
+			ASSUMEYES="yes"
+			if [[ "${DRYRUN}" = "no" ]] ; then
+				DRYRUN="yes"
+			fi
 		fi
+		if [[ -n "${WEBHOOK}" ]] && [[ "${DRYRUN}" = "no" ]] && info="$(webhookinfo "${WEBHOOK}" 2> /dev/null)" ; then
+			echo "posting as ${info//$'\n'/", "}"
+		fi
+		while prompt "${TEMPLATE:-"ptwebhookt"}> " ; do
+			read -r command args <<< "${REPLY}"
+			history -s "${command} ${args}"
+			case "${command}" in
+				("use")
+					TEMPLATE="${args}"
+				;;
+				("set")
+					if [[ "${args}" =~ ^[A-Za-z_][A-Za-z0-9_]*\ (.*)$ ]] ; then
+						OVERRIDES["${args%% *}"]="${BASH_REMATCH[1]}"
+					else
+						echo "usage: set NAME value"
+					fi
+				;;
+				("unset")
+					unset "OVERRIDES[${args:-NULL}]"
+				;;
+				("show")
+					for i in "${!OVERRIDES[@]}" ; do
+						echo "${i}=${OVERRIDES[${i}]}"
+					done
+				;;
+				("send")
+					( push ) || true
+				;;
+				("mentions")
+					for i in "${!MENTIONS[@]}" ; do
+						echo "{{@${i}}} ${MENTIONS[${i}]}"
+					done
+				;;
+				("history")
+					read -r -a SEARCH <<< "${args}"
+					searchhistory "${SEARCH[@]}"
+				;;
+				("help")
+					echo -e "use <template>\nset NAME value\nunset NAME\nshow\nsend\nmentions\nhistory [words...]\nexit"
+				;;
+				("exit"|"quit")
+					break
+				;;
+				("")
+				;;
+				(*)
+					echo "unknown command \"${command}\", try help."
+				;;
+			esac
+		done
+	;;
+	("validate")
+		for i in "${!VALIDATE[@]}" ; do
+			VALIDATE[i]="$(findtemplate "${VALIDATE[i]}")"
+			if [[ ! -f "${VALIDATE[i]}" ]] ; then
+				VALIDATE[i]="${VALIDATE[i]%.sh}.sh"
+			fi
+		done
 
-		if [[ -f "${TEMPLATE}" ]] ; then
-			source "${TEMPLATE}"
-		elif [[ -f "${TEMPLATE}.sh" ]] ; then
-			source "${TEMPLATE}.sh"
+		if [[ "${WATCH}" = "yes" ]] ; then
+			while : ; do
+				if (( "${#VALIDATE[@]}" > 0 )) ; then
+					mapfile -t files < <(printf '%s\n' "${VALIDATE[@]}")
+				else
+					mapfile -t files < <(templates)
+				fi
+				stamp="$(stat -c "%n %Y" "${files[@]}" 2> /dev/null | md5sum)"
+				if [[ "${stamp}" != "${last}" ]] ; then
+					last="${stamp}"
+					echo "== $(date +%T)"
+					validate "${files[@]}" || true
+				fi
+				sleep 1
+			done
+		elif (( "${#VALIDATE[@]}" > 0 )) ; then
+			validate "${VALIDATE[@]}"
+		else
+			mapfile -t files < <(templates)
+			validate "${files[@]}"
+		fi
+	;;
+	("list")
+		category="" HIGHLIGHT="no"
+		if [[ -t 1 ]] && [[ "${ACCESSIBLE}" != "yes" ]] ; then
+			HIGHLIGHT="yes"
+		fi
+		while IFS=$'\t' read -r name file ; do
+			if [[ "${name}" = */* ]] ; then
+				dir="${name%/*}/"
+			else
+				dir="./"
+			fi
+			if [[ -n "${CATEGORY}" ]] && [[ "${dir}" != "${CATEGORY}/"* ]] ; then
+				continue
+			fi
+			version="$(metadata "${file}" version | head -n 1)"
+			description="$(metadata "${file}" description | head -n 1)"
+			tags="$(metadata "${file}" tags | tr "," "\n" | awk '{$1 = $1} NF { printf " #%s", $0 }')"
+			if [[ -n "${TAG}" ]] && [[ "${tags} " != *" #${TAG#"#"} "* ]] ; then
+				continue
+			fi
+			base="${name##*/}" shown="${name##*/}"
+			if [[ -n "${FILTER}" ]] ; then
+				if match="$(fuzzy "${FILTER}" "${shown}")" ; then
+					shown="${match}"
+				elif match="$(fuzzy "${FILTER}" "${description}")" ; then
+					description="${match}"
+				else
+					continue
+				fi
+			fi
+			if [[ "${dir}" != "${category}" ]] ; then
+				category="${dir}"
+				echo "${category}"
+			fi
+			pin=""
+			if pinned "${file}" ; then
+				pin=" 📌"
+				if [[ "${ACCESSIBLE}" = "yes" ]] ; then
+					pin=" (pinned)"
+				fi
+			fi
+			printf '    %s%b%*s %-8s %b%s%s\n' "$(icon "${file}")" "${shown}" "$(( 24 - ${#base} ))" "" "${version}" "${description}" "${tags}" "${pin}"
+		done < <(ranked)
+	;;
+	("export")
+		if [[ -z "${BUNDLE}" ]] || [[ -z "${TEMPLATE}" ]] ; then
+			echo "usage: --template <template> --export <bundle.json>"
+			exit 1
+		fi
+		push
+	;;
+	("import")
+		if [[ ! -f "${PAYLOAD}" ]] || [[ -z "${NEWTEMPLATE}" ]] ; then
+			echo "usage: --import <message.json> <new template>"
+			exit 1
+		fi
+		mapfile -t imported < <(importable "${PAYLOAD}")
+		if [[ "${NEWTEMPLATE}" != /* ]] ; then
+			NEWTEMPLATE="${TEMPLATEDIR}/${NEWTEMPLATE}"
+		fi
+		NEWTEMPLATE="${NEWTEMPLATE%.sh}.sh"
+		if [[ -f "${NEWTEMPLATE}" ]] ; then
+			confirm "\"${NEWTEMPLATE}\" exists, overwrite it?" || exit 1
+		fi
+		mkdir -p "$(dirname "${NEWTEMPLATE}")"
+		{
+			echo "## description: imported from ${PAYLOAD##*/}"
+			echo "## version: 1.0"
+			for line in "${imported[@]}" ; do
+				IFS=$'\t' read -r name option value <<< "${line}"
+				if [[ "${name}" = "COLOR" ]] ; then
+					value="$(printf '0x%06x' "${value}")"
+				fi
+				if [[ "${name}" != "-" ]] ; then
+					printf '[[ -n "${%s}" ]] || %s=%q\n' "${name}" "${name}" "${value}"
+				fi
+			done
+			echo
+			printf 'discordsh --webhook-url="${WEBHOOK}"'
+			for line in "${imported[@]}" ; do
+				IFS=$'\t' read -r name option value <<< "${line}"
+				if [[ "${name}" != "-" ]] ; then
+					printf ' \\\n    %s "${%s}"' "${option}" "${name}"
+				else
+					printf ' \\\n    %s' "${option}"
+				fi
+			done
+			echo
+		} > "${NEWTEMPLATE}"
+		validate "${NEWTEMPLATE}"
+	;;
+	("duplicate")
+		TEMPLATE="$(findtemplate "${TEMPLATE}")"
+		TEMPLATE="${TEMPLATE%.sh}"
+		if [[ ! -f "${TEMPLATE}.sh" ]] ; then
+			echo "can't find template \"${TEMPLATE:-NULL}\"."
+			exit 1
+		fi
+		if [[ -z "${NEWTEMPLATE}" ]] && [[ -t 0 ]] ; then
+			read -r -p "name of the copy: " NEWTEMPLATE
+		fi
+		if [[ -z "${NEWTEMPLATE}" ]] ; then
+			echo "usage: --duplicate <template> <new name>"
+			exit 1
+		fi
+		if [[ "${NEWTEMPLATE}" != /* ]] ; then
+			NEWTEMPLATE="${TEMPLATEDIR}/${NEWTEMPLATE}"
+		fi
+		NEWTEMPLATE="${NEWTEMPLATE%.sh}"
+		if [[ -f "${NEWTEMPLATE}.sh" ]] ; then
+			confirm "\"${NEWTEMPLATE}.sh\" exists, overwrite it?" || exit 1
+		fi
+		mkdir -p "$(dirname "${NEWTEMPLATE}")"
+		for i in "${TEMPLATE}".* ; do
+			cp "${i}" "${NEWTEMPLATE}${i#"${TEMPLATE}"}"
+			echo "${NEWTEMPLATE}${i#"${TEMPLATE}"}"
+		done
+	;;
+	("edit")
+		if [[ -z "${TEMPLATE}" ]] ; then
+			echo "usage: --edit <template>"
+			exit 1
+		fi
+		TEMPLATE="$(findtemplate "${TEMPLATE}")"
+		if [[ ! -f "${TEMPLATE}" ]] ; then
+			TEMPLATE="${TEMPLATE%.sh}.sh"
+		fi
+		if [[ "${TEMPLATE}" = "${SHAREDDIR}/"* ]] ; then
+			echo "\"${TEMPLATE}\" is a shared template, --sync drops changes made to it."
+			confirm "edit it anyway?" || exit 1
+		fi
+		mkdir -p "$(dirname "${TEMPLATE}")"
+		${VISUAL:-${EDITOR:-vi}} "${TEMPLATE}"
+		validate "${TEMPLATE}"
+	;;
+	("tags")
+		while IFS=$'\t' read -r name file ; do
+			metadata "${file}" tags | tr "," "\n"
+		done < <(catalog) | awk '{$1 = $1} NF' | sort | uniq -c | awk '{ printf "#%s\t%s\n", $2, $1 }'
+	;;
+	("info")
+		TEMPLATE="$(findtemplate "${TEMPLATE}")"
+		if [[ ! -f "${TEMPLATE}" ]] && [[ -f "${TEMPLATE}.sh" ]] ; then
+			TEMPLATE="${TEMPLATE}.sh"
+		elif [[ ! -f "${TEMPLATE}" ]] ; then
+			echo "can't find template \"${TEMPLATE:-NULL}\"."
+			exit 1
+		fi
+		version="$(metadata "${TEMPLATE}" version | head -n 1)"
+		targets="$(metadata "${TEMPLATE}" targets | head -n 1)"
+		echo -e "template:\t$(icon "${TEMPLATE}")${TEMPLATE}\nversion:\t${version:-"-"}\ntargets:\t${targets:-"WEBHOOK"}"
+		mapfile -t changelog < <(metadata "${TEMPLATE}" changelog)
+		if (( "${#changelog[@]}" > 0 )) ; then
+			echo "changelog:"
+			printf '\t%s\n' "${changelog[@]}"
+		fi
+	;;
+	("sync")
+		if [[ -z "${TEMPLATEREPO}" ]] ; then
+			echo "set TEMPLATEREPO in \"${CONFIG}\" to the git repository of shared templates."
+			exit 1
+		fi
+		if [[ ! -d "${SHAREDDIR}/.git" ]] ; then
+			git clone --quiet "${TEMPLATEREPO}" "${SHAREDDIR}"
+		else
+			mapfile -t changed < <(git -C "${SHAREDDIR}" status --porcelain)
+			if (( "${#changed[@]}" > 0 )) ; then
+				echo "shared templates were changed locally:"
+				printf '\t%s\n' "${changed[@]}"
+				echo "copy them into \"${TEMPLATEDIR}\" to keep them, local templates win over shared ones."
+				confirm "drop the local changes and update?" || exit 1
+				git -C "${SHAREDDIR}" reset --quiet --hard
+				git -C "${SHAREDDIR}" clean --quiet -fd
+			fi
+			git -C "${SHAREDDIR}" pull --quiet --ff-only
+		fi
+		echo "shared templates are at $(git -C "${SHAREDDIR}" log -1 --format="%h %s")."
+	;;
+	("pending")
+		for i in "${PENDINGDIR}"/* ; do
+			if [[ -f "${i}" ]] ; then
+				jq -r --arg id "${i##*/}" --arg requester "$(stat -c %U "${i}")" '"\($id)\t\($requester)\t\(.template)"' "${i}" 2> /dev/null \
+					|| echo -e "${i##*/}\t$(stat -c %U "${i}")\tunreadable"
+			fi
+		done
+	;;
+	("approve"|"reject")
+		if [[ ! "${PENDINGID}" =~ ^[0-9-]+$ ]] || [[ ! -f "${PENDINGDIR}/${PENDINGID}" ]] ; then
+			echo "can't find pending message \"${PENDINGID:-NULL}\"."
+			exit 1
+		fi
+		if [[ "${SETOPT}" = "approve" ]] ; then
+			if ! jq -e 'type == "object"' "${PENDINGDIR}/${PENDINGID}" &> /dev/null ; then
+				echo "can't read pending message \"${PENDINGID}\"."
+				exit 1
+			fi
+			i="$(stat -c %U "${PENDINGDIR}/${PENDINGID}")"
+			if [[ "${SELFAPPROVE}" = "no" ]] && [[ "${i}" = "$(id -un)" ]] ; then
+				echo "this message needs approval from someone else than ${i}."
+				exit 1
+			fi
+			approve "${PENDINGDIR}/${PENDINGID}" || exit 1
+			if [[ "${DRYRUN}" != "no" ]] || [[ -n "${BUNDLE}" ]] ; then
+				exit 0
+			fi
+		fi
+		rm -rf "${PENDINGDIR:?}/${PENDINGID}" "${PENDINGDIR:?}/${PENDINGID}.files"
+	;;
+	("view")
+		if [[ -f "${PAYLOAD}" ]] ; then
+			preview "${PAYLOAD}"
 		else
-			echo "can't find template find \"${TEMPLATE:-NULL}\"."
+			echo "can't find payload \"${PAYLOAD:-NULL}\"."
+			exit 1
+		fi
+	;;
+	("history")
+		searchhistory "${SEARCH[@]}"
+	;;
+	("delete")
+		if [[ ! "${MESSAGEID}" =~ ^[0-9]+$ ]] ; then
+			echo "usage: --delete <message id>"
+			exit 1
+		fi
+		if [[ -n "${INTERACTION}" ]] ; then
+			WEBHOOK="https://discord.com/api/webhooks/${INTERACTION}"
+		fi
+		if [[ -z "${WEBHOOK}" ]] ; then
+			echo "can't find webhook \"${WEBHOOK:-NULL}\"."
+			exit 1
+		fi
+		confirm "delete message ${MESSAGEID}?" || exit 1
+		if ! request -X DELETE "$(threaded "$(address "${WEBHOOK%%\?*}")/messages/${MESSAGEID}")" ; then
+			echo "can't delete message \"${MESSAGEID}\"."
+			exit 1
+		fi
+		echo "message ${MESSAGEID} deleted."
+	;;
+	("webhook-info")
+		if [[ -z "${WEBHOOK}" ]] ; then
+			echo "can't find webhook \"${WEBHOOK:-NULL}\"."
+			exit 1
+		fi
+		if ! webhookinfo "${WEBHOOK}" ; then
+			echo "can't reach webhook, check WEBHOOK."
+			exit 1
+		fi
+	;;
+	("datetime")
+		token="$(datetime "${DATETIME}" t)" || exit 1
+		unix="${token:3:-3}"
+		for style in t T d D f F R ; do
+			printf '%-20s %s\n' "<t:${unix}:${style}>" "$(humantime "${unix}" "${style}")"
+		done
+	;;
+	("resend")
+		if [[ ! "${RESEND}" =~ ^[0-9]+$ ]] || ! line="$(sed -n "${RESEND}p" "${HISTORYFILE}" 2> /dev/null)" || [[ -z "${line}" ]] ; then
+			echo "can't find history entry \"${RESEND:-NULL}\"."
+			exit 1
+		fi
+		TEMPLATE="$(cut -f 2 <<< "${line}")"
+		line="${line#*$'\t'*$'\t'}"
+		eval "discordsh ${line%%$'\t'*}"
+	;;
+	("set")
+		if [[ ! "${SETVAR}" =~ ^[A-Za-z_][A-Za-z0-9_]*= ]] ; then
+			echo "usage: --set NAME=value"
 			exit 1
 		fi
+		mkdir -p "${GLOBALS%/*}"
+		if [[ -f "${GLOBALS}" ]] ; then
+			sed -i "/^${SETVAR%%=*}=/d" "${GLOBALS}"
+		fi
+		if [[ -n "${SETVAR#*=}" ]] ; then
+			printf '%s=%q\n' "${SETVAR%%=*}" "${SETVAR#*=}" >> "${GLOBALS}"
+		fi
+	;;
+	("globals")
+		if [[ -f "${GLOBALS}" ]] ; then
+			cat "${GLOBALS}"
+		fi
+	;;
+	("export-state")
+		if [[ -z "${STATEFILE}" ]] ; then
+			echo "usage: --export-state <file.tar.gz> [--no-secrets]"
+			exit 1
+		fi
+		tmp="${WORKDIR}/state"
+		mkdir -p "${tmp}/config"
+		if [[ -d "${CONFIGDIR}" ]] ; then
+			cp -r "${CONFIGDIR}/." "${tmp}/config/"
+		fi
+		if [[ -f "${CONFIG}" ]] ; then
+			cp "${CONFIG}" "${tmp}/conf.sh"
+		fi
+		if [[ "${SECRETS}" = "no" ]] ; then
+			find "${tmp}" -name "*.gpg" -delete
+			while IFS= read -r -d "" file ; do
+				scrub "${file}"
+			done < <(find "${tmp}" -name ".git" -prune -o -type f -print0)
+		fi
+		tar -czf "${STATEFILE}" -C "${tmp}" .
+		echo "state exported to \"${STATEFILE}\"."
+	;;
+	("import-state")
+		if [[ ! -f "${STATEFILE}" ]] ; then
+			echo "can't find state archive \"${STATEFILE:-NULL}\"."
+			exit 1
+		fi
+		tmp="${WORKDIR}/state"
+		mkdir -p "${tmp}"
+		tar -xzf "${STATEFILE}" -C "${tmp}"
+		mkdir -p "${CONFIGDIR}"
+		cp -r "${tmp}/config/." "${CONFIGDIR}/"
+		if [[ -f "${tmp}/conf.sh" ]] ; then
+			if [[ ! -f "${CONFIG}" ]] || confirm "overwrite \"${CONFIG}\"?" ; then
+				cp "${tmp}/conf.sh" "${CONFIG}"
+			fi
+		fi
+		echo "state imported from \"${STATEFILE}\"."
+	;;
+	("snippets")
+		if [[ -d "${SNIPPETDIR}" ]] ; then
+			for i in "${SNIPPETDIR}"/* ; do
+				if [[ -f "${i}" ]] ; then
+					echo -e "${i##*/}\t$(head -n 1 "${i}")"
+				fi
+			done
+		fi
 	;;
 	("help")
-		echo "basic usage"
+		cat <<-EOF
+		basic usage:
+		    --push --template <template>    send the given template
+		    --templates-dir <dir>           where to look for templates
+		    --lang <locale>                 use the template's <template>.<locale>.conf, defaults to $LANG
+		    --history [words...]            search sent messages by date, template or text
+		    --since <date> / --until <date> only show history from this date on / up to this date
+		    --resend <number>               send a message from the history again
+		    --delete <message id>           delete a message the webhook sent
+		    --datetime [date]               Discord timestamps for a date ("tomorrow 15:00") in every style
+		    --webhook-info                  show the webhook's name, avatar, channel and guild
+		    --set <NAME=value>              keep a global variable for every template, empty value removes it
+		    --globals                       list global variables
+		    --list [category]               list templates by directory, pinned and higher priority first
+		    --filter <letters>              only list templates whose name or description fuzzy-match
+		    --tag <tag>                     only list templates with the tag
+		    --tags                          list every tag and how many templates have it
+		    --export <bundle.json>          write the template, its values and the payload to one file
+		    --import <message.json> <name>  make a template out of a Discord message, payload or bundle
+		    --duplicate <template> [name]   copy a template and its conf files under a new name
+		    --edit <template>               open a template in \$EDITOR and validate it afterwards
+		    --info <template>               show a template's version and changelog
+		    --sync                          clone or update shared templates from TEMPLATEREPO
+		    --pending                       list messages waiting for approval
+		    --approve <id> / --reject <id>  send or drop a pending message
+		    --shell                         interactive prompt: use <template>, set NAME value, send, history
+		    --replay <script>               run --shell lines from a file at a fixed pace, with --dry-run and --yes
+		    --interaction <app_id>/<token>  send as an interaction follow-up instead of to WEBHOOK
+		    --split                         send an over-long description as several messages, "part 1/3"...
+		    --to <target>                   send to this webhook or TARGETS name instead, repeatable
+		    --thread <id>                   send into a thread or forum post of the webhook's channel
+		    --thread-name <name>            start a new forum post with this title (forum channels only)
+		    --flags "<flags>"               Discord message flags: silent, suppress-embeds
+		    --attach <file>                 attach a file (a screenshot, a log...), repeatable
+		    --vars <vars.env>               read KEY=value pairs (.env or flat .toml) as {{vars.key}} (repeatable)
+		    --yes                           don't ask for confirmation
+		    --accessible                    plain output without box-drawing characters
+		    --dry-run                       print the request (method, URL and payload) instead of sending
+		    --json                          print the exact JSON payload instead of sending
+		    --edit-json                     edit the JSON payload in \$EDITOR before it is sent
+		    --validate [templates...]       check templates for errors and Discord's limits, add --watch to re-check on every save
+		    --view <payload.json>           show a webhook payload file
+		    --export-state <file.tar.gz>    bundle conf.sh, snippets and globals, add --no-secrets to leave credentials out
+		    --import-state <file.tar.gz>    restore a bundle made by --export-state
+		    --snippets                      list snippets in "${SNIPPETDIR}"
+		EOF
 	;;
-esac
\ No newline at end of file
+esac